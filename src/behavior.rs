@@ -1,14 +1,123 @@
+use crate::context::Context;
+use crate::error::AnalyzerError;
+use crate::report::{Column, Report};
 use clap::ValueEnum;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::str::FromStr;
 
-/// Every “behavior” (functionality) must implement this trait.
+/// Every “behavior” (functionality) must implement this trait; what it
+/// declares about itself is its `BehaviorInfo`.
+///
+/// A behavior only computes: `run` returns its typed result and writes
+/// nothing. Rendering is a separate step: every result converts into a
+/// `Report`, which `Report::write` renders in any `OutputFormat` (and the
+/// combined `html`/`markdown` reports and `export-sqlite` take as is).
 pub trait Behavior {
-    /// Returns the kind of behavior, so you can filter/group them.
-    fn behavior_type(&self) -> BehaviorType;
+    /// The typed result, e.g. `DailyCounts` for `count-daily`.
+    type Output;
 
-    /// Run the analysis/operation.  
-    /// (Right now, stubs—all the real work is `// TODO`.)
-    fn run(&self) -> Result<(), Box<dyn Error>>;
+    /// Run the analysis/operation and return its results.
+    /// JSON-based behaviors get their (filtered) messages from `ctx`.
+    fn run(&self, ctx: &Context) -> Result<Self::Output, Box<dyn Error>>;
+}
+
+/// A behavior whose result comes back as a `Report`, whatever its `Output`:
+/// what the registry builds, so `all`/`group` can run behaviors side by side.
+/// Every `Behavior` whose output converts into a `Report` is one.
+pub trait DynBehavior {
+    fn report(&self, ctx: &Context) -> Result<Report, Box<dyn Error>>;
+}
+
+impl<B> DynBehavior for B
+where
+    B: Behavior,
+    for<'a> Report: From<&'a B::Output>,
+{
+    fn report(&self, ctx: &Context) -> Result<Report, Box<dyn Error>> {
+        Ok(Report::from(&self.run(ctx)?))
+    }
+}
+
+/// What a behavior declares about itself. Its subcommand, its place in
+/// `all`/`group`, the `list-behaviors` listing and the help text are all
+/// generated from this; `behaviors::registry()` lists every entry (the
+/// built-in `behaviors::REGISTRY`, then the loaded plugins).
+pub struct BehaviorInfo {
+    /// Subcommand and config-file name, e.g. `count-daily`; also the `behavior` of its reports.
+    pub name: &'static str,
+    /// One line, shown as the subcommand's help.
+    pub description: &'static str,
+    pub behavior_type: BehaviorType,
+    /// `all`/`group` skip the behavior when one of these wasn't given.
+    pub inputs: &'static [Input],
+    /// Settings besides the inputs and output.
+    pub options: &'static [BehaviorOption],
+    /// Output schema: results are written as `<file_stem>.<ext>`, with these columns.
+    pub file_stem: &'static str,
+    pub columns: &'static [Column],
+    /// Stamped on the behavior's reports; bumped whenever its columns or
+    /// summary change in a way that breaks readers.
+    pub schema_version: u32,
+    /// Builds the behavior. `None` for behaviors with a hand-written subcommand
+    /// that `all`/`group` don't run (`search` needs a query).
+    pub build: Option<BuildFn>,
+}
+
+/// Builds the behavior `info` describes from its inputs and options.
+pub type BuildFn = fn(&'static BehaviorInfo, &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>>;
+
+/// A behavior setting: `--<name>` on its subcommand, `<name> = …` in its
+/// `[behavior.<name>]` config table.
+pub struct BehaviorOption {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+/// What a behavior is built from. `BehaviorInfo::build` is only called with
+/// the behavior's declared inputs present.
+#[derive(Debug, Clone, Default)]
+pub struct BehaviorArgs {
+    pub json: Vec<String>,
+    pub folder: Option<String>,
+    pub file: Option<String>,
+    /// Option values by name, as given on the command line or in the config file.
+    pub options: BTreeMap<String, String>,
+}
+
+impl BehaviorArgs {
+    /// The value of option `name`, if set.
+    pub fn option<T: FromStr>(&self, name: &str) -> Result<Option<T>, Box<dyn Error>> {
+        match self.options.get(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| AnalyzerError::Usage(format!("Invalid value “{}” for option {}", value, name)).into()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// An input a behavior can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// One or more exports, or an index (`--json`)
+    Json,
+    /// A folder to scan (`--folder`)
+    Folder,
+    /// A single file (`--file`)
+    File,
+}
+
+impl Input {
+    /// The command-line option that supplies this input.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Input::Json => "--json",
+            Input::Folder => "--folder",
+            Input::File => "--file",
+        }
+    }
 }
 
 /// Enumerates all behavior‐kinds.  
@@ -26,4 +135,8 @@ pub enum BehaviorType {
     Diffusion,       // diffusion
     Shares,          // shares
     TextStats,       // text-stats
+    Pii,             // detect-pii
+    Search,          // search
+    Script,          // script
+    Plugin,          // plugins that don't declare a type of their own
 }
@@ -1,24 +1,98 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::Message;
+use crate::report::{Column, ColumnType, Report};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::error::Error;
 
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "count-daily",
+    description: "From JSON, count how many events occurred each day",
+    behavior_type: BehaviorType::Daily,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "messages_per_day",
+    columns: &[
+        Column::new("date", ColumnType::Date),
+        Column::new("count", ColumnType::Integer),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(CountDaily::new(args.json.clone())))
+}
+
+/// CountDaily behavior: counts (filtered) messages per calendar day in the
+/// run's timezone (UTC by default), one row per day (`date`, `count`) in
+/// chronological order. Messages without a parsable date are skipped.
 pub struct CountDaily {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
 }
 
 impl CountDaily {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+        }
     }
 }
 
 impl Behavior for CountDaily {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::Daily
+    type Output = DailyCounts;
+
+    fn run(&self, ctx: &Context) -> Result<DailyCounts, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(count_daily(&messages, ctx.timezone))
     }
+}
+
+/// Messages per day, as computed by `count_daily`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyCounts {
+    /// Days with at least one message, in chronological order.
+    pub days: Vec<DayCount>,
+    /// Messages counted (those with a date).
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub count: usize,
+}
+
+/// Counts `messages` per calendar day in `timezone`.
+pub fn count_daily(messages: &[Message], timezone: Tz) -> DailyCounts {
+    let mut per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for date in messages.iter().filter_map(|m| m.date) {
+        *per_day.entry(date.with_timezone(&timezone).date_naive()).or_default() += 1;
+    }
+    DailyCounts {
+        messages: per_day.values().sum(),
+        days: per_day.into_iter().map(|(date, count)| DayCount { date, count }).collect(),
+    }
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, parse timestamps, count events per day, print counts
-        println!("CountDaily: would count daily events in \"{}\"...", self.json_path);
-        Ok(())
+impl From<&DailyCounts> for Report {
+    fn from(result: &DailyCounts) -> Self {
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows: result.days.iter().map(|d| vec![d.date.into(), d.count.into()]).collect(),
+            summary: vec![("messages", result.messages.into()), ("days", result.days.len().into())],
+            summary_line: format!(
+                "→ CountDaily: Analyzed {} messages over {} days",
+                result.messages,
+                result.days.len()
+            ),
+        }
     }
 }
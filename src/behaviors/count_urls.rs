@@ -1,128 +1,164 @@
 // src/behaviors/count_urls.rs
 
-use crate::behavior::{Behavior, BehaviorType};
-use crate::common::OutputFormat; // shared enum: Stdout, Txt, Csv
-use regex::Regex;
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::error::AnalyzerError;
+use crate::model::{url_regex, Message};
+use crate::report::{Column, ColumnType, Report};
+use rayon::prelude::*;
+use serde::Serialize;
 use std::collections::BTreeMap;
 use std::error::Error;
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "count-urls",
+    description: "Extract all URLs and print each URL with the number of times it appeared",
+    behavior_type: BehaviorType::UrlCount,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "urls_count",
+    columns: &[
+        Column::new("count", ColumnType::Integer),
+        Column::new("url", ColumnType::Text),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(CountUrls::new(args.json.clone())))
+}
 
 /// CountUrls behavior:
 ///
-///   - Reads `input_path` line by line, finds every substring matching `https?://…`.
+///   - Loads the (filtered) messages of `input_paths` and collects every URL
+///     they contain (link entities, or `https?://…` matches in plain text).
+///   - A single input that isn't JSON is read as plain text instead, line by
+///     line, counting its `https?://…` matches; message filters don't apply.
 ///   - Tallies how many times each URL appears.
-///   - If `format` is `Stdout`, prints `count<TAB>URL` lines to stdout.
-///   - If `format` is `Txt`, writes `urls_count.txt` in `output_dir` with tab-separated lines + summary.
-///   - If `format` is `Csv`, writes `urls_count.csv` in `output_dir` with a CSV header + quoted pairs + summary.
+///   - Returns one count per URL (`UrlCounts`), most frequent first; as a
+///     report, one `count`/`url` row each (`urls_count.<ext>` for file formats).
 pub struct CountUrls {
-    pub input_path: String,
-    pub output_dir: Option<String>,
-    pub format: OutputFormat,
+    pub input_paths: Vec<String>,
 }
 
 impl CountUrls {
-    /// Constructor. If `format` is `None`, defaults to `OutputFormat::Stdout`.
-    pub fn new(
-        input_path: String,
-        output_dir: Option<String>,
-        format: Option<OutputFormat>,
-    ) -> Self {
-        let fmt = format.unwrap_or(OutputFormat::Stdout);
-        CountUrls {
-            input_path,
-            output_dir,
-            format: fmt,
-        }
+    pub fn new(input_paths: Vec<String>) -> Self {
+        CountUrls { input_paths }
     }
 }
 
 impl Behavior for CountUrls {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::UrlCount
+    type Output = UrlCounts;
+
+    fn run(&self, ctx: &Context) -> Result<UrlCounts, Box<dyn Error>> {
+        let messages = match (ctx.messages(&self.input_paths), self.input_paths.as_slice()) {
+            (Ok(messages), _) => messages,
+            (Err(e), [path]) if is_not_json(e.as_ref()) => {
+                if ctx.pseudonymizer().is_some() {
+                    return Err(AnalyzerError::Usage(format!(
+                        "{} isn't a JSON export, and --pseudonymize can't scrub plain text",
+                        path
+                    ))
+                    .into());
+                }
+                eprintln!("→ CountUrls: {} isn't JSON; counting the URLs of its lines", path);
+                return count_urls_in_text(path);
+            }
+            (Err(e), _) => return Err(e),
+        };
+        Ok(count_urls(&messages))
     }
+}
+
+/// Whether loading failed because the input isn't JSON at all.
+fn is_not_json(e: &(dyn Error + 'static)) -> bool {
+    matches!(e.downcast_ref::<AnalyzerError>(), Some(AnalyzerError::MalformedJson { .. }))
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // 1) Validate that the input file exists
-        let path = Path::new(&self.input_path);
-        if !path.is_file() {
-            return Err(format!("Input file not found: {}", &self.input_path).into());
+/// Tallies the `https?://…` matches of a plain text file, line by line.
+pub fn count_urls_in_text(path: &str) -> Result<UrlCounts, Box<dyn Error>> {
+    let file = File::open(path).map_err(AnalyzerError::io(path))?;
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(AnalyzerError::io(path))?;
+        for url in url_regex().find_iter(&line) {
+            *counts.entry(url.as_str().to_string()).or_default() += 1;
         }
+    }
+    Ok(sorted(counts))
+}
 
-        // 2) Open input_path for reading
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+/// How often each URL appears, as computed by `count_urls`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlCounts {
+    /// One entry per distinct URL, most frequent first, then by URL.
+    pub urls: Vec<UrlCount>,
+}
 
-        // 3) Prepare a regex that matches http:// or https:// URLs
-        let url_regex = Regex::new(r"https?://[A-Za-z0-9\./\?=\+_\-]+")?;
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlCount {
+    pub url: String,
+    pub count: usize,
+}
 
-        // 4) Count occurrences in a BTreeMap
-        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
-        for line_res in reader.lines() {
-            let line = line_res?;
-            for mat in url_regex.find_iter(&line) {
-                let url = mat.as_str().to_string();
+/// Tallies the URLs of `messages` (see `Message::links`), in parallel.
+pub fn count_urls(messages: &[Message]) -> UrlCounts {
+    // 1) Count occurrences per chunk of messages, then add the chunks up
+    let counts = messages
+        .par_iter()
+        .fold(BTreeMap::new, |mut counts: BTreeMap<String, usize>, msg| {
+            for url in msg.links() {
                 *counts.entry(url).or_default() += 1;
             }
-        }
-
-        // 5) Convert to a Vec and sort by count descending, then URL lex
-        let mut entries: Vec<(&String, &usize)> = counts.iter().collect();
-        entries.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
-
-        let unique_count = entries.len();
-
-        // 6) Branch on chosen format
-        match self.format {
-            OutputFormat::Stdout => {
-                // Print to stdout
-                for (url, cnt) in &entries {
-                    println!("{}\t{}", cnt, url);
-                }
-                println!("→ CountUrls: Found {} unique URLs (printed to stdout)", unique_count);
+            counts
+        })
+        .reduce(BTreeMap::new, |mut counts, other| {
+            for (url, n) in other {
+                *counts.entry(url).or_default() += n;
             }
+            counts
+        });
 
-            OutputFormat::Txt => {
-                // Must have an output_dir to write a .txt file
-                let dir = self.output_dir.as_deref().unwrap_or(".");
-                fs::create_dir_all(dir)?;
-                let mut output_path = PathBuf::from(dir);
-                output_path.push("urls_count.txt");
-
-                let mut f = File::create(&output_path)?;
-                for (url, cnt) in &entries {
-                    writeln!(f, "{}\t{}", cnt, url)?;
-                }
-                writeln!(f, "→ CountUrls: Found {} unique URLs", unique_count)?;
+    // 2) Sort by count descending, then URL lex
+    sorted(counts)
+}
 
-                println!(
-                    "→ CountUrls: Results saved to {}",
-                    output_path.display()
-                );
-            }
+fn sorted(counts: BTreeMap<String, usize>) -> UrlCounts {
+    let mut urls: Vec<UrlCount> = counts.into_iter().map(|(url, count)| UrlCount { url, count }).collect();
+    urls.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.url.cmp(&b.url)));
+    UrlCounts { urls }
+}
 
-            OutputFormat::Csv => {
-                // Must have an output_dir to write a .csv file
-                let dir = self.output_dir.as_deref().unwrap_or(".");
-                fs::create_dir_all(dir)?;
-                let mut output_path = PathBuf::from(dir);
-                output_path.push("urls_count.csv");
-
-                let mut f = File::create(&output_path)?;
-                writeln!(f, "\"Count\",\"URL\"")?;
-                for (url, cnt) in &entries {
-                    writeln!(f, "\"{}\",\"{}\"", cnt, url)?;
-                }
-                writeln!(f, "\"→ CountUrls: Found {} unique URLs\",\"\"", unique_count)?;
-                
-                println!(
-                    "→ CountUrls: Results saved to {}",
-                    output_path.display()
-                );
-            }
+impl From<&UrlCounts> for Report {
+    fn from(result: &UrlCounts) -> Self {
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows: result.urls.iter().map(|u| vec![u.count.into(), u.url.as_str().into()]).collect(),
+            summary: vec![("unique_urls", result.urls.len().into())],
+            summary_line: format!("→ CountUrls: Found {} unique URLs", result.urls.len()),
         }
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn counts_the_urls_of_a_plain_text_file() {
+        let path = std::env::temp_dir().join(format!("count-urls-{}.txt", std::process::id()));
+        fs::write(&path, "see https://a.example/x and http://b.example\nhttps://a.example/x again\nnothing\n").unwrap();
+        let counts = count_urls_in_text(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        let urls: Vec<(&str, usize)> = counts.urls.iter().map(|u| (u.url.as_str(), u.count)).collect();
+        assert_eq!(urls, [("https://a.example/x", 2), ("http://b.example", 1)]);
     }
 }
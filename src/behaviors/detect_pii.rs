@@ -0,0 +1,210 @@
+// src/behaviors/detect_pii.rs
+
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorOption, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::Message;
+use crate::pii::{self, PiiKind};
+use crate::report::{Cell, Column, ColumnType, Report};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "detect-pii",
+    description: "From JSON, find personal data (phones, emails, IBANs, cards, wallets, IPs...) in text and file names",
+    behavior_type: BehaviorType::Pii,
+    inputs: &[Input::Json],
+    options: &[BehaviorOption {
+        name: "samples",
+        help: "How many masked examples to list per row (default 0: none)",
+    }],
+    file_stem: "pii",
+    columns: &[
+        Column::new("level", ColumnType::Category),
+        Column::new("pii_type", ColumnType::Category),
+        Column::new("user_id", ColumnType::Text),
+        Column::new("message_id", ColumnType::Integer),
+        Column::new("count", ColumnType::Integer),
+        Column::new("samples", ColumnType::Text),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    let mut b = DetectPii::new(args.json.clone());
+    if let Some(n) = args.option("samples")? {
+        b.samples = n;
+    }
+    Ok(Box::new(b))
+}
+
+/// DetectPii behavior: scans the text and attachment file names of the
+/// (filtered) messages for personal data (see `pii::find`): phone numbers,
+/// emails, IBANs, card numbers, crypto wallet addresses, passport-like
+/// numbers and IP addresses.
+///
+///   - Counts what it finds per type, per user (`from_id`) and per message;
+///     rows are tagged with their `level` (`type`, `user` or `message`).
+///   - With `samples`, lists that many masked examples per row (see `pii::mask`);
+///     the values themselves are never written.
+///
+/// Run it before `--pseudonymize --scrub-text`, which removes phones and emails.
+pub struct DetectPii {
+    pub json_paths: Vec<String>,
+    pub samples: usize,
+}
+
+impl DetectPii {
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+            samples: 0,
+        }
+    }
+}
+
+impl Behavior for DetectPii {
+    type Output = PiiFindings;
+
+    fn run(&self, ctx: &Context) -> Result<PiiFindings, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(detect_pii(&messages, self.samples))
+    }
+}
+
+/// Personal data found, as computed by `detect_pii`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PiiFindings {
+    /// Messages scanned.
+    pub messages: usize,
+    /// Messages with any personal data.
+    pub messages_with_pii: usize,
+    /// One entry per type found, in `PiiKind` order.
+    pub by_type: Vec<PiiCount>,
+    /// One entry per user and type, most findings first.
+    pub by_user: Vec<PiiCount>,
+    /// One entry per message and type, in message order.
+    pub by_message: Vec<PiiCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PiiCount {
+    pub kind: PiiKind,
+    /// Set for user and message entries (`None` for messages without a sender).
+    pub user_id: Option<String>,
+    /// Set for message entries.
+    pub message_id: Option<i64>,
+    pub count: usize,
+    /// Up to the requested number of masked examples, in message order.
+    pub samples: Vec<String>,
+}
+
+impl PiiFindings {
+    /// Everything found, all types.
+    pub fn total(&self) -> usize {
+        self.by_type.iter().map(|c| c.count).sum()
+    }
+}
+
+/// Scans `messages` (in parallel) for personal data, keeping up to `samples`
+/// masked examples per entry.
+pub fn detect_pii(messages: &[Message], samples: usize) -> PiiFindings {
+    // 1) What each message holds, per type: its text, then its file names
+    let scanned: Vec<BTreeMap<PiiKind, Vec<String>>> = messages
+        .par_iter()
+        .map(|msg| {
+            let mut found: BTreeMap<PiiKind, Vec<String>> = BTreeMap::new();
+            let texts = [Some(msg.text.as_str()), msg.file.as_deref(), msg.photo.as_deref()];
+            for text in texts.into_iter().flatten() {
+                for f in pii::find(text) {
+                    found.entry(f.kind).or_default().push(pii::mask(f.value));
+                }
+            }
+            found
+        })
+        .collect();
+
+    // 2) Tally per message, per user and per type
+    let entry = |kind, user_id: Option<&String>, message_id| PiiCount {
+        kind,
+        user_id: user_id.cloned(),
+        message_id,
+        count: 0,
+        samples: Vec::new(),
+    };
+    let add = |count: &mut PiiCount, values: &[String]| {
+        count.count += values.len();
+        let room = samples.saturating_sub(count.samples.len());
+        count.samples.extend(values.iter().take(room).cloned());
+    };
+    let mut by_type: BTreeMap<PiiKind, PiiCount> = BTreeMap::new();
+    let mut by_user: BTreeMap<(Option<&String>, PiiKind), PiiCount> = BTreeMap::new();
+    let mut by_message = Vec::new();
+    for (msg, found) in messages.iter().zip(&scanned) {
+        let user = msg.from_id.as_ref();
+        for (&kind, values) in found {
+            add(by_type.entry(kind).or_insert_with(|| entry(kind, None, None)), values);
+            add(by_user.entry((user, kind)).or_insert_with(|| entry(kind, user, None)), values);
+            let mut count = entry(kind, user, Some(msg.id));
+            add(&mut count, values);
+            by_message.push(count);
+        }
+    }
+
+    // 3) Most findings first among users
+    let mut by_user: Vec<PiiCount> = by_user.into_values().collect();
+    by_user.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| (&a.user_id, a.kind).cmp(&(&b.user_id, b.kind))));
+    PiiFindings {
+        messages: messages.len(),
+        messages_with_pii: scanned.iter().filter(|found| !found.is_empty()).count(),
+        by_type: by_type.into_values().collect(),
+        by_user,
+        by_message,
+    }
+}
+
+impl From<&PiiFindings> for Report {
+    fn from(result: &PiiFindings) -> Self {
+        let levels = [("type", &result.by_type), ("user", &result.by_user), ("message", &result.by_message)];
+        let rows: Vec<Vec<Cell>> = levels
+            .iter()
+            .flat_map(|(level, counts)| {
+                counts.iter().map(move |c| {
+                    vec![
+                        (*level).into(),
+                        c.kind.as_str().into(),
+                        c.user_id.clone().into(),
+                        c.message_id.into(),
+                        c.count.into(),
+                        c.samples.join(", ").into(),
+                    ]
+                })
+            })
+            .collect();
+        let per_type = |kind: PiiKind| result.by_type.iter().find(|c| c.kind == kind).map_or(0, |c| c.count);
+        let mut summary: Vec<(&'static str, Cell)> = vec![
+            ("messages", result.messages.into()),
+            ("messages_with_pii", result.messages_with_pii.into()),
+        ];
+        summary.extend(PiiKind::ALL.iter().map(|kind| (kind.as_str(), per_type(*kind).into())));
+        let details: Vec<String> = result.by_type.iter().map(|c| format!("{}: {}", c.kind.as_str(), c.count)).collect();
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows,
+            summary,
+            summary_line: format!(
+                "→ DetectPii: Found {} items of personal data in {} of {} messages{}",
+                result.total(),
+                result.messages_with_pii,
+                result.messages,
+                if details.is_empty() { String::new() } else { format!(" ({})", details.join(", ")) }
+            ),
+        }
+    }
+}
@@ -1,27 +1,182 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::behaviors::shares::{media_name, ShareKind};
+use crate::context::Context;
+use crate::model::Message;
+use crate::report::{Cell, Column, ColumnType, Report};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "diffusion",
+    description: "From JSON, output which users shared which media files and links & how many times each link was shared",
+    behavior_type: BehaviorType::Diffusion,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "diffusion",
+    columns: &[
+        Column::new("kind", ColumnType::Category),
+        Column::new("value", ColumnType::Text),
+        Column::new("shares", ColumnType::Integer),
+        Column::new("distinct_users", ColumnType::Integer),
+        Column::new("first_shared_by", ColumnType::Text),
+        Column::new("first_shared_at", ColumnType::Timestamp),
+        Column::new("users", ColumnType::Text),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(Diffusion::new(args.json.clone())))
+}
+
+/// Diffusion behavior: how far each link and media file spread.
+///
+/// For every link and media file: how many times it was shared, by how many
+/// distinct users, who shared it first and when, and the list of sharers
+/// (comma-separated `from_id`s). Rows are sorted most-shared first.
 pub struct Diffusion {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
 }
 
 impl Diffusion {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+        }
     }
 }
 
 impl Behavior for Diffusion {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::Diffusion
+    type Output = DiffusionStats;
+
+    fn run(&self, ctx: &Context) -> Result<DiffusionStats, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(diffusion(&messages))
     }
+}
+
+/// How far each item spread, as computed by `diffusion`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffusionStats {
+    /// Most shared first, then links before media, then by value.
+    pub items: Vec<ItemSpread>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemSpread {
+    pub kind: ShareKind,
+    /// The URL, or the attachment (see `media_name`).
+    pub value: String,
+    pub shares: usize,
+    /// `from_id` of every sharer, sorted (`-` for messages without one).
+    pub users: Vec<String>,
+    /// The earliest dated share: who and when.
+    pub first_shared_by: Option<String>,
+    pub first_shared_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Spread<'a> {
+    shares: usize,
+    users: BTreeSet<&'a str>,
+    first: Option<(DateTime<Utc>, &'a str)>,
+}
+
+impl<'a> Spread<'a> {
+    /// Counts a share by `user`; ties for the first share go to the earlier message.
+    fn add(&mut self, user: &'a str, date: Option<DateTime<Utc>>) {
+        self.shares += 1;
+        self.users.insert(user);
+        if let Some(date) = date {
+            if self.first.is_none_or(|(first, _)| date < first) {
+                self.first = Some((date, user));
+            }
+        }
+    }
+
+    /// Adds the spread within the chunk of messages that follows this one.
+    fn merge(&mut self, next: Spread<'a>) {
+        self.shares += next.shares;
+        self.users.extend(next.users);
+        if let Some((date, user)) = next.first {
+            if self.first.is_none_or(|(first, _)| date < first) {
+                self.first = Some((date, user));
+            }
+        }
+    }
+}
+
+/// Aggregates the links and media of `messages` per item. Chunks of messages
+/// are aggregated in parallel, then merged in order.
+pub fn diffusion(messages: &[Message]) -> DiffusionStats {
+    // 1) Aggregate per (kind, value)
+    let spread = messages
+        .par_iter()
+        .fold(BTreeMap::new, |mut spread: BTreeMap<(ShareKind, String), Spread>, msg| {
+            let user = msg.from_id.as_deref().unwrap_or("-");
+            let items = msg
+                .links()
+                .into_iter()
+                .map(|url| (ShareKind::Link, url))
+                .chain(media_name(msg).map(|f| (ShareKind::Media, f.to_string())));
+            for key in items {
+                spread.entry(key).or_default().add(user, msg.date);
+            }
+            spread
+        })
+        .reduce(BTreeMap::new, |mut spread, next| {
+            for (key, s) in next {
+                spread.entry(key).or_default().merge(s);
+            }
+            spread
+        });
+
+    // 2) Most shared first
+    let mut items: Vec<ItemSpread> = spread
+        .into_iter()
+        .map(|((kind, value), s)| ItemSpread {
+            kind,
+            value,
+            shares: s.shares,
+            users: s.users.iter().map(|u| u.to_string()).collect(),
+            first_shared_by: s.first.map(|(_, user)| user.to_string()),
+            first_shared_at: s.first.map(|(date, _)| date),
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.shares));
+    DiffusionStats { items }
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, for each user, collect:
-        //       - which media files they shared
-        //       - which links they shared
-        //       - how many times each link was shared
-        println!("Diffusion: would analyze media+link diffusion in \"{}\"...", self.json_path);
-        Ok(())
+impl From<&DiffusionStats> for Report {
+    fn from(result: &DiffusionStats) -> Self {
+        let rows: Vec<Vec<Cell>> = result
+            .items
+            .iter()
+            .map(|item| {
+                vec![
+                    item.kind.as_str().into(),
+                    item.value.as_str().into(),
+                    item.shares.into(),
+                    item.users.len().into(),
+                    item.first_shared_by.clone().into(),
+                    item.first_shared_at.into(),
+                    item.users.join(",").into(),
+                ]
+            })
+            .collect();
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            summary: vec![("items", rows.len().into())],
+            summary_line: format!("→ Diffusion: {} distinct links and media files", rows.len()),
+            rows,
+        }
     }
 }
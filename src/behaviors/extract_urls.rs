@@ -1,24 +1,98 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::Message;
+use crate::report::{Column, ColumnType, Report};
+use serde::Serialize;
 use std::error::Error;
 
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "extract-urls",
+    description: "Extract URLs from JSON and print each one",
+    behavior_type: BehaviorType::Url,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "urls",
+    columns: &[
+        Column::new("message_id", ColumnType::Integer),
+        Column::new("url", ColumnType::Text),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(ExtractUrls::new(args.json.clone())))
+}
+
+/// ExtractUrls behavior: lists every URL of every (filtered) message, in
+/// message order, one row per occurrence (`message_id`, `url`).
 pub struct ExtractUrls {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
 }
 
 impl ExtractUrls {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+        }
     }
 }
 
 impl Behavior for ExtractUrls {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::Url
+    type Output = ExtractedUrls;
+
+    fn run(&self, ctx: &Context) -> Result<ExtractedUrls, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(extract_urls(&messages))
+    }
+}
+
+/// Every URL occurrence, as computed by `extract_urls`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedUrls {
+    /// In message order; a URL appears once per message that contains it.
+    pub urls: Vec<UrlOccurrence>,
+    /// Messages searched.
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlOccurrence {
+    pub message_id: i64,
+    pub url: String,
+}
+
+/// Collects the URLs of `messages` (see `Message::links`).
+pub fn extract_urls(messages: &[Message]) -> ExtractedUrls {
+    let urls = messages
+        .iter()
+        .flat_map(|m| m.links().into_iter().map(|url| UrlOccurrence { message_id: m.id, url }))
+        .collect();
+    ExtractedUrls {
+        urls,
+        messages: messages.len(),
     }
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, parse JSON, extract each URL and print it
-        println!("ExtractUrls: would process JSON at \"{}\"...", self.json_path);
-        Ok(())
+impl From<&ExtractedUrls> for Report {
+    fn from(result: &ExtractedUrls) -> Self {
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows: result
+                .urls
+                .iter()
+                .map(|u| vec![u.message_id.into(), u.url.as_str().into()])
+                .collect(),
+            summary: vec![("urls", result.urls.len().into()), ("messages", result.messages.into())],
+            summary_line: format!(
+                "→ ExtractUrls: Found {} URLs in {} messages",
+                result.urls.len(),
+                result.messages
+            ),
+        }
     }
 }
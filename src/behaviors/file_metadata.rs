@@ -1,24 +1,110 @@
-use crate::behavior::{Behavior, BehaviorType};
+// src/behaviors/file_metadata.rs
+
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::error::AnalyzerError;
+use crate::report::{Column, ColumnType, Report};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "file-metadata",
+    description: "From a single file, get metadata (name, format, size) and print it",
+    behavior_type: BehaviorType::FileMetadata,
+    inputs: &[Input::File],
+    options: &[],
+    file_stem: "file_metadata",
+    columns: &[
+        Column::new("path", ColumnType::Text),
+        Column::new("name", ColumnType::Text),
+        Column::new("extension", ColumnType::Category),
+        Column::new("size_bytes", ColumnType::Integer),
+        Column::new("modified", ColumnType::Timestamp),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    let file = args.file.clone().ok_or("file-metadata needs --file")?;
+    Ok(Box::new(FileMetadata::new(file)))
+}
 
+/// FileMetadata behavior: one row with the file's path, name, extension,
+/// size in bytes and last modification time (UTC).
 pub struct FileMetadata {
     pub file_path: String,
 }
 
 impl FileMetadata {
     pub fn new(file_path: String) -> Self {
-        Self { file_path }
+        Self {
+            file_path,
+        }
     }
 }
 
 impl Behavior for FileMetadata {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::FileMetadata
+    type Output = FileInfo;
+
+    fn run(&self, _ctx: &Context) -> Result<FileInfo, Box<dyn Error>> {
+        file_metadata(&self.file_path)
     }
+}
+
+/// A file's metadata, as read by `file_metadata`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileInfo {
+    pub path: String,
+    pub name: Option<String>,
+    /// Lowercased, without the dot.
+    pub extension: Option<String>,
+    pub size_bytes: u64,
+    /// Last modification time, if the platform reports one.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Reads the metadata of the file at `file_path`.
+pub fn file_metadata(file_path: &str) -> Result<FileInfo, Box<dyn Error>> {
+    let path = Path::new(file_path);
+    if !path.is_file() {
+        return Err(AnalyzerError::MissingInput {
+            what: "Input file",
+            path: file_path.to_string(),
+        }
+        .into());
+    }
+    let metadata = fs::metadata(path).map_err(AnalyzerError::io(path))?;
+    Ok(FileInfo {
+        path: file_path.to_string(),
+        name: path.file_name().map(|n| n.to_string_lossy().into_owned()),
+        extension: path.extension().map(|e| e.to_string_lossy().to_lowercase()),
+        size_bytes: metadata.len(),
+        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+    })
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: stat self.file_path, extract name, extension, size, print them
-        println!("FileMetadata: would inspect file \"{}\" to get name, format, size...", self.file_path);
-        Ok(())
+impl From<&FileInfo> for Report {
+    fn from(info: &FileInfo) -> Self {
+        let row = vec![
+            info.path.as_str().into(),
+            info.name.clone().into(),
+            info.extension.clone().into(),
+            info.size_bytes.into(),
+            info.modified.into(),
+        ];
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows: vec![row],
+            summary: vec![("size_bytes", info.size_bytes.into())],
+            summary_line: format!("→ FileMetadata: {} ({} bytes)", info.path, info.size_bytes),
+        }
     }
 }
@@ -1,24 +1,157 @@
-use crate::behavior::{Behavior, BehaviorType};
+// src/behaviors/list_extensions.rs
+
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::error::AnalyzerError;
+use crate::progress;
+use crate::report::{Column, ColumnType, Report};
+use indicatif::HumanBytes;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "list-extensions",
+    description: "From a folder, get all file extensions and print a list of occurrences per extension",
+    behavior_type: BehaviorType::Extensions,
+    inputs: &[Input::Folder],
+    options: &[],
+    file_stem: "extensions",
+    columns: &[
+        Column::new("extension", ColumnType::Category),
+        Column::new("count", ColumnType::Integer),
+        Column::new("total_bytes", ColumnType::Integer),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    let folder = args.folder.clone().ok_or("list-extensions needs --folder")?;
+    Ok(Box::new(ListExtensions::new(folder)))
+}
 
+/// ListExtensions behavior:
+///
+///   - Walks `folder_path` recursively (e.g. an export's media folders).
+///   - Counts files and total bytes per extension (lowercased; files without
+///     one are listed as `(none)`).
+///   - Rows are sorted by file count, most common first.
 pub struct ListExtensions {
     pub folder_path: String,
 }
 
 impl ListExtensions {
     pub fn new(folder_path: String) -> Self {
-        Self { folder_path }
+        Self {
+            folder_path,
+        }
     }
 }
 
 impl Behavior for ListExtensions {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::Extensions
+    type Output = ExtensionCounts;
+
+    fn run(&self, _ctx: &Context) -> Result<ExtensionCounts, Box<dyn Error>> {
+        list_extensions(Path::new(&self.folder_path))
+    }
+}
+
+/// Files per extension, as computed by `list_extensions`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionCounts {
+    /// Most common first, then by extension.
+    pub extensions: Vec<ExtensionCount>,
+    /// Files found.
+    pub files: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionCount {
+    /// Lowercased, without the dot; `(none)` for files without one.
+    pub extension: String,
+    pub files: usize,
+    pub total_bytes: u64,
+}
+
+/// Tallies the files under `root` (recursively) by extension.
+pub fn list_extensions(root: &Path) -> Result<ExtensionCounts, Box<dyn Error>> {
+    if !root.is_dir() {
+        return Err(AnalyzerError::MissingInput {
+            what: "Folder",
+            path: root.display().to_string(),
+        }
+        .into());
     }
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: scan self.folder_path recursively, collect file extensions, print extension → count
-        println!("ListExtensions: would scan folder \"{}\" for extensions...", self.folder_path);
-        Ok(())
+    // 1) Tally extensions over the whole tree
+    let bar = progress::spinner(&format!("walk {}", root.display()));
+    let (mut files, mut bytes) = (0usize, 0u64);
+    let mut extensions: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let read_error = AnalyzerError::io(&dir);
+        let entries = fs::read_dir(&dir).and_then(|entries| entries.collect::<io::Result<Vec<_>>>());
+        for entry in entries.map_err(read_error)? {
+            let file_type = entry.file_type().map_err(AnalyzerError::io(entry.path()))?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                let path = entry.path();
+                let extension = path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_else(|| "(none)".to_string());
+                let size = entry.metadata().map_err(AnalyzerError::io(&path))?.len();
+                let stats = extensions.entry(extension).or_default();
+                stats.0 += 1;
+                stats.1 += size;
+                files += 1;
+                bytes += size;
+                bar.set_message(format!("{} files, {}", files, HumanBytes(bytes)));
+            }
+        }
+    }
+    bar.finish_and_clear();
+
+    // 2) Most common first
+    let mut extensions: Vec<ExtensionCount> = extensions
+        .into_iter()
+        .map(|(extension, (files, total_bytes))| ExtensionCount {
+            extension,
+            files,
+            total_bytes,
+        })
+        .collect();
+    extensions.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.extension.cmp(&b.extension)));
+    Ok(ExtensionCounts {
+        files: extensions.iter().map(|e| e.files).sum(),
+        extensions,
+    })
+}
+
+impl From<&ExtensionCounts> for Report {
+    fn from(result: &ExtensionCounts) -> Self {
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows: result
+                .extensions
+                .iter()
+                .map(|e| vec![e.extension.as_str().into(), e.files.into(), e.total_bytes.into()])
+                .collect(),
+            summary: vec![("files", result.files.into()), ("extensions", result.extensions.len().into())],
+            summary_line: format!(
+                "→ ListExtensions: {} files with {} distinct extensions",
+                result.files,
+                result.extensions.len()
+            ),
+        }
     }
 }
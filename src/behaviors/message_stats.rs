@@ -1,25 +1,90 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::Message;
+use crate::report::{Cell, Column, ColumnType, Report};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::error::Error;
 
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "message-stats",
+    description: "From JSON, get total number of messages sent and number of distinct users",
+    behavior_type: BehaviorType::MessageStats,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "message_stats",
+    columns: &[
+        Column::new("metric", ColumnType::Category),
+        Column::new("value", ColumnType::Integer),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(MessageStats::new(args.json.clone())))
+}
+
+/// MessageStats behavior: counts the messages sent (service messages excluded)
+/// and the distinct senders (by `from_id`) among them, as `metric`/`value` rows.
 pub struct MessageStats {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
 }
 
 impl MessageStats {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+        }
     }
 }
 
 impl Behavior for MessageStats {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::MessageStats
+    type Output = MessageTotals;
+
+    fn run(&self, ctx: &Context) -> Result<MessageTotals, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(message_stats(&messages))
+    }
+}
+
+/// Totals computed by `message_stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageTotals {
+    /// Messages sent (service messages excluded).
+    pub messages: usize,
+    /// Distinct senders (by `from_id`) among them.
+    pub distinct_users: usize,
+}
+
+/// Counts the messages sent and their distinct senders.
+pub fn message_stats(messages: &[Message]) -> MessageTotals {
+    let sent: Vec<_> = messages.iter().filter(|m| !m.is_service()).collect();
+    let users: HashSet<&str> = sent.iter().filter_map(|m| m.from_id.as_deref()).collect();
+    MessageTotals {
+        messages: sent.len(),
+        distinct_users: users.len(),
     }
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, count "number of messages sent" and
-        //       "number of distinct users who sent messages"
-        println!("MessageStats: would compute message & distinct-user counts in \"{}\"...", self.json_path);
-        Ok(())
+impl From<&MessageTotals> for Report {
+    fn from(result: &MessageTotals) -> Self {
+        let summary: Vec<(&str, Cell)> = vec![
+            ("messages", result.messages.into()),
+            ("distinct_users", result.distinct_users.into()),
+        ];
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows: summary.iter().map(|(metric, value)| vec![(*metric).into(), value.clone()]).collect(),
+            summary,
+            summary_line: format!(
+                "→ MessageStats: {} messages from {} distinct users",
+                result.messages, result.distinct_users
+            ),
+        }
     }
 }
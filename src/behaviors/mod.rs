@@ -9,15 +9,61 @@ pub mod message_stats;
 pub mod diffusion;
 pub mod shares;
 pub mod text_stats;
+pub mod detect_pii;
+pub mod search;
+pub mod script;
 
-pub use extract_urls::ExtractUrls;
-pub use count_urls::CountUrls;
-pub use count_time_slots::CountTimeSlots;
-pub use count_daily::CountDaily;
-pub use list_extensions::ListExtensions;
-pub use file_metadata::FileMetadata;
-pub use user_interactions::UserInteractions;
-pub use message_stats::MessageStats;
-pub use diffusion::Diffusion;
-pub use shares::Shares;
-pub use text_stats::TextStats;
+// Each module has the behavior, its registry entry (`INFO`) and a function
+// computing the analysis as a typed result (`count_daily::count_daily`, ...).
+pub use extract_urls::{ExtractUrls, ExtractedUrls, UrlOccurrence};
+pub use count_urls::{CountUrls, UrlCount, UrlCounts};
+pub use count_time_slots::{CountTimeSlots, SlotCount, TimeSlotCounts};
+pub use count_daily::{CountDaily, DailyCounts, DayCount};
+pub use list_extensions::{ExtensionCount, ExtensionCounts, ListExtensions};
+pub use file_metadata::{FileInfo, FileMetadata};
+pub use user_interactions::{UserActivity, UserInteractionStats, UserInteractions};
+pub use message_stats::{MessageStats, MessageTotals};
+pub use diffusion::{Diffusion, DiffusionStats, ItemSpread};
+pub use shares::{Share, ShareKind, ShareList, Shares};
+pub use text_stats::{TextStatistics, TextStats};
+pub use detect_pii::{DetectPii, PiiCount, PiiFindings};
+pub use search::{Hit, Search, SearchFormat, SearchOptions, SearchResults};
+pub use script::{Script, ScriptResult};
+
+use crate::behavior::BehaviorInfo;
+use crate::plugin;
+use std::sync::OnceLock;
+
+/// Every behavior, in the order `all` runs them and `list-behaviors` lists
+/// them. A new behavior is a module above plus its `INFO` here.
+pub const REGISTRY: &[&BehaviorInfo] = &[
+    &extract_urls::INFO,
+    &count_urls::INFO,
+    &count_time_slots::INFO,
+    &count_daily::INFO,
+    &list_extensions::INFO,
+    &file_metadata::INFO,
+    &user_interactions::INFO,
+    &message_stats::INFO,
+    &diffusion::INFO,
+    &shares::INFO,
+    &text_stats::INFO,
+    &detect_pii::INFO,
+    &search::INFO,
+    &script::INFO,
+];
+
+/// `REGISTRY` followed by the plugins loaded so far (see `plugin::load`).
+/// Taken once: load plugins before the first call.
+pub fn registry() -> &'static [&'static BehaviorInfo] {
+    static ALL: OnceLock<Vec<&'static BehaviorInfo>> = OnceLock::new();
+    ALL.get_or_init(|| {
+        let plugins = plugin::plugins().iter().map(|p| p.info);
+        REGISTRY.iter().copied().chain(plugins).collect()
+    })
+}
+
+/// The registry entry called `name`.
+pub fn find(name: &str) -> Option<&'static BehaviorInfo> {
+    registry().iter().copied().find(|info| info.name == name)
+}
@@ -0,0 +1,361 @@
+// src/behaviors/script.rs
+
+use crate::behavior::{Behavior, BehaviorInfo, BehaviorType, Input};
+use crate::context::Context;
+use crate::report::{Cell, Column, Report};
+use std::error::Error;
+
+/// Registry entry (see `BehaviorInfo`). The columns are whatever the script emits.
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "script",
+    description: "Run a Rhai script over the messages and write the rows it emits",
+    behavior_type: BehaviorType::Script,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "script_results",
+    columns: &[],
+    schema_version: 1,
+    build: None,
+};
+
+/// The functions a script can call, shown under `script --help`.
+pub const API: &str = "Script API (Rhai, https://rhai.rs):
+  messages                 the (filtered) messages: `for m in messages { ... }`, `messages.len()`
+  m.id, m.type, m.chat     id, “message”/“service”, chat name
+  m.timestamp, m.date      Unix seconds and RFC 3339 text (() without a date)
+  m.from, m.from_id        sender name and id (() if unknown)
+  m.text, m.links          text, and its URLs as an array
+  m.media_type, m.has_media, m.forwarded_from, m.reply_to, m.is_service, m.is_bot
+  canonical_url(url)       URL without tracking parameters, www., fragment, trailing slash
+  domain(url)              host of the canonical URL
+  bucket(timestamp, unit)  time bucket in the run's timezone; unit is one of
+                           30min, hour, day, week, month, weekday, hour-of-day
+  words(text)              lowercased words
+  columns([\"a\", \"b\"])      column order (default: the keys of the first row)
+  emit(#{a: 1, b: \"x\"})    add a row (a map by column, or an array in column order)
+  summary(key, value)      add a summary value
+  summary_line(text)       replace the “→ Script: …” line
+  print(x)                 goes to stderr";
+
+/// Operations a script may run by default (see `Script::max_operations`).
+pub const DEFAULT_MAX_OPERATIONS: u64 = 1_000_000_000;
+
+/// How deep script functions may call each other.
+pub const MAX_CALL_LEVELS: usize = 64;
+
+/// Script behavior: runs a Rhai script (`--file`) over the (filtered)
+/// messages. The script gets them as typed values plus a few helpers (see
+/// `API`) and `emit`s rows, which are written like any behavior's.
+/// Needs a build with the `script` feature.
+pub struct Script {
+    pub file: String,
+    pub json_paths: Vec<String>,
+    /// A script that runs more operations than this (0: no limit), or nests
+    /// calls deeper than `MAX_CALL_LEVELS`, fails instead of running on.
+    pub max_operations: u64,
+}
+
+impl Script {
+    pub fn new(file: String, json_paths: Vec<String>) -> Self {
+        Self {
+            file,
+            json_paths,
+            max_operations: DEFAULT_MAX_OPERATIONS,
+        }
+    }
+
+    pub fn with_max_operations(mut self, max_operations: u64) -> Self {
+        self.max_operations = max_operations;
+        self
+    }
+}
+
+/// What a script emitted, typed: a column's type is that of its first non-empty value.
+#[derive(Debug, Clone)]
+pub struct ScriptResult {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Cell>>,
+    pub summary: Vec<(&'static str, Cell)>,
+    pub summary_line: String,
+}
+
+impl Behavior for Script {
+    type Output = ScriptResult;
+
+    fn run(&self, ctx: &Context) -> Result<ScriptResult, Box<dyn Error>> {
+        #[cfg(feature = "script")]
+        {
+            let messages = ctx.messages(&self.json_paths)?;
+            engine::run(&self.file, messages, ctx.timezone, self.max_operations)
+        }
+        #[cfg(not(feature = "script"))]
+        {
+            let _ = ctx;
+            Err("Scripts need a build with the `script` feature (cargo build --features script)".into())
+        }
+    }
+}
+
+impl From<&ScriptResult> for Report {
+    fn from(result: &ScriptResult) -> Self {
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: result.columns.clone(),
+            rows: result.rows.clone(),
+            summary: result.summary.clone(),
+            summary_line: result.summary_line.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "script")]
+mod engine {
+    use super::{ScriptResult, MAX_CALL_LEVELS};
+    use crate::behaviors::count_time_slots::slot_label;
+    use crate::common::leak;
+    use crate::error::AnalyzerError;
+    use crate::model::{canonical_url, tokenize, Message};
+    use crate::report::{Cell, Column, ColumnType};
+    use chrono::{DateTime, Datelike, Timelike};
+    use chrono_tz::Tz;
+    use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, INT};
+    use std::cell::RefCell;
+    use std::error::Error;
+    use std::fs;
+    use std::path::Path;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    /// The `messages` a script iterates over; yields each message by value.
+    #[derive(Clone)]
+    struct Messages(Arc<Vec<Message>>);
+
+    struct MessagesIter {
+        messages: Arc<Vec<Message>>,
+        next: usize,
+    }
+
+    impl IntoIterator for Messages {
+        type Item = Message;
+        type IntoIter = MessagesIter;
+
+        fn into_iter(self) -> MessagesIter {
+            MessagesIter {
+                messages: self.0,
+                next: 0,
+            }
+        }
+    }
+
+    impl Iterator for MessagesIter {
+        type Item = Message;
+
+        fn next(&mut self) -> Option<Message> {
+            let m = self.messages.get(self.next)?.clone();
+            self.next += 1;
+            Some(m)
+        }
+    }
+
+    /// Rows and summary collected from `emit`, `summary` and friends.
+    #[derive(Default)]
+    struct Emitted {
+        columns: Vec<String>,
+        rows: Vec<Vec<Dynamic>>,
+        summary: Vec<(String, Dynamic)>,
+        summary_line: Option<String>,
+    }
+
+    pub fn run(
+        file: &str,
+        messages: Arc<Vec<Message>>,
+        timezone: Tz,
+        max_operations: u64,
+    ) -> Result<ScriptResult, Box<dyn Error>> {
+        let fail = |message: String| AnalyzerError::Script {
+            path: file.to_string(),
+            message,
+        };
+        let source = fs::read_to_string(file).map_err(AnalyzerError::io(file))?;
+        let count = messages.len();
+
+        // 1) The engine with the message type and helpers
+        let emitted = Rc::new(RefCell::new(Emitted::default()));
+        let mut engine = Engine::new();
+        // A runaway script fails instead of hanging the run
+        engine.set_max_operations(max_operations);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        engine.on_print(|s| eprintln!("{}", s));
+        engine.on_debug(|s, _, pos| eprintln!("{:?}: {}", pos, s));
+        register_message(&mut engine);
+        engine
+            .register_type_with_name::<Messages>("Messages")
+            .register_iterator::<Messages>()
+            .register_fn("len", |m: &mut Messages| m.0.len() as INT);
+        engine.register_fn("canonical_url", |url: &str| canonical_url(url));
+        engine.register_fn("domain", |url: &str| domain(&canonical_url(url)));
+        engine.register_fn("words", |text: &str| -> Array { tokenize(text).map(Dynamic::from).collect() });
+        engine.register_fn("bucket", move |ts: INT, unit: &str| bucket(ts, unit, timezone));
+        let e = Rc::clone(&emitted);
+        engine.register_fn("columns", move |names: Array| {
+            e.borrow_mut().columns = names.into_iter().map(|n| n.to_string()).collect();
+        });
+        let e = Rc::clone(&emitted);
+        engine.register_fn("emit", move |row: Map| {
+            let mut e = e.borrow_mut();
+            if e.columns.is_empty() {
+                e.columns = row.keys().map(|k| k.to_string()).collect();
+            }
+            let cells = e.columns.iter().map(|c| row.get(c.as_str()).cloned().unwrap_or(Dynamic::UNIT)).collect();
+            e.rows.push(cells);
+        });
+        let e = Rc::clone(&emitted);
+        engine.register_fn("emit", move |row: Array| e.borrow_mut().rows.push(row));
+        let e = Rc::clone(&emitted);
+        engine.register_fn("summary", move |key: &str, value: Dynamic| {
+            e.borrow_mut().summary.push((key.to_string(), value));
+        });
+        let e = Rc::clone(&emitted);
+        engine.register_fn("summary_line", move |line: &str| e.borrow_mut().summary_line = Some(line.to_string()));
+
+        // 2) Run it
+        let ast = engine.compile(&source).map_err(|e| fail(e.to_string()))?;
+        let mut scope = Scope::new();
+        scope.push_constant("messages", Messages(messages));
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(|e| match *e {
+            EvalAltResult::ErrorTooManyOperations(_) => fail(format!(
+                "{}; the limit is {}: raise --max-operations, or 0 for no limit",
+                e, max_operations
+            )),
+            _ => fail(e.to_string()),
+        })?;
+        drop(engine);
+
+        // 3) Type what it emitted (names are leaked: one run, a handful of them)
+        let emitted = Rc::try_unwrap(emitted).map_err(|_| "script engine still running")?.into_inner();
+        let width = emitted.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut names = emitted.columns;
+        names.extend((names.len()..width).map(|n| format!("column_{}", n + 1)));
+        let rows: Vec<Vec<Cell>> = emitted
+            .rows
+            .into_iter()
+            .map(|row| {
+                let mut cells: Vec<Cell> = row.into_iter().map(cell).collect();
+                cells.resize(names.len(), Cell::Null);
+                cells
+            })
+            .collect();
+        let columns = names
+            .into_iter()
+            .enumerate()
+            .map(|(n, name)| {
+                let first = rows.iter().map(|r| &r[n]).find(|c| **c != Cell::Null);
+                let kind = match first {
+                    Some(Cell::Int(_)) => ColumnType::Integer,
+                    Some(Cell::Float(_)) => ColumnType::Float,
+                    _ => ColumnType::Text,
+                };
+                Column::new(leak(name), kind)
+            })
+            .collect();
+        let name = Path::new(file).file_name().map_or(file.into(), |n| n.to_string_lossy());
+        Ok(ScriptResult {
+            summary_line: emitted.summary_line.unwrap_or_else(|| {
+                format!("→ Script: {} emitted {} rows from {} messages", name, rows.len(), count)
+            }),
+            summary: emitted.summary.into_iter().map(|(k, v)| (leak(k), cell(v))).collect(),
+            columns,
+            rows,
+        })
+    }
+
+    fn register_message(engine: &mut Engine) {
+        let optional = |value: Option<String>| value.map_or(Dynamic::UNIT, Dynamic::from);
+        engine
+            .register_type_with_name::<Message>("Message")
+            .register_get("id", |m: &mut Message| m.id as INT)
+            .register_get("type", |m: &mut Message| m.kind.as_str().to_string())
+            .register_get("chat", |m: &mut Message| m.chat.label())
+            .register_get("timestamp", |m: &mut Message| {
+                m.date.map_or(Dynamic::UNIT, |d| Dynamic::from(d.timestamp() as INT))
+            })
+            .register_get("date", move |m: &mut Message| optional(m.date.map(|d| d.to_rfc3339())))
+            .register_get("from", move |m: &mut Message| optional(m.from.clone()))
+            .register_get("from_id", move |m: &mut Message| optional(m.from_id.clone()))
+            .register_get("text", |m: &mut Message| m.text.clone())
+            .register_get("links", |m: &mut Message| -> Array { m.links().into_iter().map(Dynamic::from).collect() })
+            .register_get("media_type", move |m: &mut Message| optional(m.media_type.clone()))
+            .register_get("has_media", |m: &mut Message| m.has_media())
+            .register_get("forwarded_from", move |m: &mut Message| optional(m.forwarded_from.clone()))
+            .register_get("reply_to", |m: &mut Message| {
+                m.reply_to_message_id.map_or(Dynamic::UNIT, |id| Dynamic::from(id as INT))
+            })
+            .register_get("is_service", |m: &mut Message| m.is_service())
+            .register_get("is_bot", |m: &mut Message| m.is_bot());
+    }
+
+    fn domain(url: &str) -> String {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        rest.split(['/', '?']).next().unwrap_or_default().to_string()
+    }
+
+    /// The label of the `unit` bucket `ts` (Unix seconds) falls in, in `timezone`.
+    fn bucket(ts: INT, unit: &str, timezone: Tz) -> Result<String, Box<EvalAltResult>> {
+        let dt = DateTime::from_timestamp(ts, 0)
+            .ok_or_else(|| format!("timestamp out of range: {}", ts))?
+            .with_timezone(&timezone);
+        Ok(match unit {
+            "30min" => slot_label(dt.hour() as usize * 2 + (dt.minute() >= 30) as usize),
+            "hour" => dt.format("%Y-%m-%d %H:00").to_string(),
+            "day" => dt.format("%Y-%m-%d").to_string(),
+            "week" => format!("{}-W{:02}", dt.iso_week().year(), dt.iso_week().week()),
+            "month" => dt.format("%Y-%m").to_string(),
+            "weekday" => dt.format("%a").to_string(),
+            "hour-of-day" => format!("{:02}", dt.hour()),
+            other => return Err(format!("unknown bucket “{}” (see `script --help`)", other).into()),
+        })
+    }
+
+    fn cell(value: Dynamic) -> Cell {
+        if value.is_unit() {
+            Cell::Null
+        } else if let Ok(n) = value.as_int() {
+            Cell::Int(n)
+        } else if let Ok(x) = value.as_float() {
+            Cell::Float(x)
+        } else {
+            Cell::Text(value.to_string())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "script"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Arc;
+
+    fn run(name: &str, source: &str, max_operations: u64) -> Result<ScriptResult, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("{}-{}.rhai", name, std::process::id()));
+        fs::write(&path, source).unwrap();
+        let result = engine::run(path.to_str().unwrap(), Arc::new(Vec::new()), chrono_tz::UTC, max_operations);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn runaway_scripts_stop() {
+        let e = run("loop", "let n = 0; loop { n += 1; }", 1000).unwrap_err();
+        assert!(e.to_string().contains("raise --max-operations"), "{}", e);
+        let e = run("recursion", "fn f(x) { f(x + 1) } f(0);", 0).unwrap_err();
+        assert!(e.to_string().contains("Stack overflow"), "{}", e);
+    }
+
+    #[test]
+    fn scripts_within_the_limit_run() {
+        let result = run("count", "let n = 0; for i in 0..100 { n += i; } emit(#{ n: n });", 10_000).unwrap();
+        assert_eq!(result.rows, [[Cell::Int(4950)]]);
+    }
+}
@@ -0,0 +1,450 @@
+// src/behaviors/search.rs
+
+use crate::behavior::{Behavior, BehaviorInfo, BehaviorType, Input};
+use crate::context::Context;
+use crate::filter::MessageFilter;
+use crate::index::{self, Index};
+use crate::model::Message;
+use crate::common::OutputFormat;
+use crate::report::{create_output, Cell, Column, ColumnType, Report};
+use clap::{Args, ValueEnum};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use serde_json::json;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Output format of `search`: human-readable text, a JSON document, JSON Lines
+/// (one hit per line), or a flat table of hit/context rows as CSV, TSV or Markdown.
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+pub enum SearchFormat {
+    Text,
+    Json,
+    Jsonl,
+    Csv,
+    Tsv,
+    Markdown,
+}
+
+/// How the query is matched against message text.
+#[derive(Debug, Clone, Default, Args)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression instead of a literal string.
+    #[arg(long)]
+    pub regex: bool,
+
+    /// Match case-insensitively.
+    #[arg(short = 'i', long)]
+    pub ignore_case: bool,
+
+    /// Number of messages of the same chat to show before and after each match.
+    #[arg(short = 'C', long, default_value_t = 0)]
+    pub context: usize,
+}
+
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "search",
+    description: "Search message text (literal or regex) and print each match with optional context",
+    behavior_type: BehaviorType::Search,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "search_results",
+    columns: &[
+        Column::new("hit", ColumnType::Integer),
+        Column::new("role", ColumnType::Category),
+        Column::new("chat_id", ColumnType::Integer),
+        Column::new("chat", ColumnType::Text),
+        Column::new("id", ColumnType::Integer),
+        Column::new("date", ColumnType::Timestamp),
+        Column::new("from", ColumnType::Text),
+        Column::new("from_id", ColumnType::Text),
+        Column::new("text", ColumnType::Text),
+    ],
+    // Needs a query, so only runs as its own `search` subcommand
+    schema_version: 1,
+    build: None,
+};
+
+/// Search behavior:
+///
+///   - Loads every message of `json_paths` and keeps those that pass the
+///     global filter and whose text matches `query` (literal, or a regex
+///     with `--regex`).
+///   - Each hit carries up to `context` neighbouring messages of the same chat
+///     before and after it, whether or not they pass the filter.
+///   - If `json_paths` is a single index directory, only the messages the index
+///     reports as candidates (plus their context) are read.
+///   - Returns the hits (`SearchResults`), which render as text, JSON or a
+///     table (`SearchResults::write`).
+pub struct Search {
+    pub json_paths: Vec<String>,
+    pub query: String,
+    pub options: SearchOptions,
+}
+
+/// What a search found, as returned by `search` and `Search::run`.
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    pub query: String,
+    /// In export order (chat by chat).
+    pub hits: Vec<Hit>,
+    /// Messages searched.
+    pub searched: usize,
+}
+
+/// One matching message plus its surrounding context.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub before: Vec<Message>,
+    pub message: Message,
+    pub after: Vec<Message>,
+}
+
+#[derive(Serialize)]
+struct MessageView<'a> {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    chat_id: Option<i64>,
+    chat: Option<&'a str>,
+    date: Option<String>,
+    from: Option<&'a str>,
+    from_id: Option<&'a str>,
+    text: &'a str,
+    edited: Option<String>,
+    forwarded_from: Option<&'a str>,
+    reply_to_message_id: Option<i64>,
+    media_type: Option<&'a str>,
+    source: Option<&'a str>,
+}
+
+impl<'a> From<&'a Message> for MessageView<'a> {
+    fn from(m: &'a Message) -> Self {
+        MessageView {
+            id: m.id,
+            kind: m.kind.as_str(),
+            chat_id: m.chat.id,
+            chat: m.chat.name.as_deref(),
+            date: m.date.map(|d| d.to_rfc3339()),
+            from: m.from.as_deref(),
+            from_id: m.from_id.as_deref(),
+            text: &m.text,
+            edited: m.edited.map(|d| d.to_rfc3339()),
+            forwarded_from: m.forwarded_from.as_deref(),
+            reply_to_message_id: m.reply_to_message_id,
+            media_type: m.media_type.as_deref(),
+            source: m.source.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HitView<'a> {
+    #[serde(rename = "match")]
+    message: MessageView<'a>,
+    before: Vec<MessageView<'a>>,
+    after: Vec<MessageView<'a>>,
+}
+
+impl Search {
+    pub fn new(json_paths: Vec<String>, query: String, options: SearchOptions) -> Self {
+        Search {
+            json_paths,
+            query,
+            options,
+        }
+    }
+
+    /// Same as `find_hits`, but reads only candidate docs (and their context) from an index.
+    fn find_hits_indexed(
+        &self,
+        index: &Index,
+        filter: &MessageFilter,
+        re: &Regex,
+    ) -> Result<Vec<Hit>, Box<dyn Error>> {
+        let literal = (!self.options.regex).then_some(self.query.as_str());
+        let Some(candidates) = index.candidates(filter, literal)? else {
+            // Nothing narrows the search: one pass over the whole store
+            return Ok(find_hits(&index.load_all()?, filter, re, self.options.context));
+        };
+
+        // 1) Read the live candidates in one pass and keep the matches (as run, position)
+        let runs = index.chat_runs();
+        let live: Vec<u32> = runs.iter().flatten().copied().filter(|d| candidates.contains(d)).collect();
+        let mut read = index.read_many(&live)?;
+        let mut matches = Vec::new();
+        for (r, run) in runs.iter().enumerate() {
+            for (i, doc) in run.iter().enumerate() {
+                if read.get(doc).is_some_and(|m| filter.matches(m) && re.is_match(&m.text)) {
+                    matches.push((r, i));
+                }
+            }
+        }
+
+        // 2) Then the context around them that isn't read yet, in a second pass
+        let context = self.options.context;
+        let window = |r: usize, i: usize| {
+            let run = &runs[r];
+            (i.saturating_sub(context), i, (i + 1 + context).min(run.len()))
+        };
+        let missing: Vec<u32> = matches
+            .iter()
+            .flat_map(|&(r, i)| {
+                let (from, _, to) = window(r, i);
+                runs[r][from..to].iter().copied()
+            })
+            .filter(|d| !read.contains_key(d))
+            .collect();
+        read.extend(index.read_many(&missing)?);
+
+        // 3) The hits, chat by chat
+        let messages = |docs: &[u32]| docs.iter().map(|d| read[d].clone()).collect::<Vec<_>>();
+        Ok(matches
+            .iter()
+            .map(|&(r, i)| {
+                let (from, at, to) = window(r, i);
+                Hit {
+                    before: messages(&runs[r][from..at]),
+                    message: read[&runs[r][at]].clone(),
+                    after: messages(&runs[r][at + 1..to]),
+                }
+            })
+            .collect())
+    }
+}
+
+impl Behavior for Search {
+    type Output = SearchResults;
+
+    /// Searches `json_paths` with the global filter of `ctx` (through the index when given one).
+    fn run(&self, ctx: &Context) -> Result<SearchResults, Box<dyn Error>> {
+        // Compile the query first so a bad regex fails before loading the export
+        let re = matcher(&self.query, &self.options)?;
+        let mut results = match self.json_paths.as_slice() {
+            [path] if index::is_index(path) => {
+                let index = Index::open(path)?;
+                SearchResults {
+                    query: self.query.clone(),
+                    hits: self.find_hits_indexed(&index, &ctx.filter, &re)?,
+                    searched: index.live_count(),
+                }
+            }
+            paths => {
+                // Unfiltered, so context can show messages the filter drops
+                let messages = ctx.unfiltered_messages(paths)?;
+                SearchResults {
+                    query: self.query.clone(),
+                    hits: find_hits(&messages, &ctx.filter, &re, self.options.context),
+                    searched: messages.len(),
+                }
+            }
+        };
+        for hit in &mut results.hits {
+            ctx.pseudonymize(&mut hit.before);
+            ctx.pseudonymize(std::slice::from_mut(&mut hit.message));
+            ctx.pseudonymize(&mut hit.after);
+        }
+        Ok(results)
+    }
+}
+
+impl SearchResults {
+    /// Renders the results in `format`: to stdout without an `output_dir`,
+    /// otherwise into `<output_dir>/search_results.<ext>`, whose path is returned.
+    pub fn write(&self, format: &SearchFormat, output_dir: Option<&str>) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        let Some(dir) = output_dir else {
+            self.render(format, &mut io::stdout().lock())?;
+            return Ok(None);
+        };
+        let extension = match format {
+            SearchFormat::Text => "txt",
+            SearchFormat::Json => "json",
+            SearchFormat::Jsonl => "jsonl",
+            SearchFormat::Csv => "csv",
+            SearchFormat::Tsv => "tsv",
+            SearchFormat::Markdown => "md",
+        };
+        let (path, mut out) = create_output(Some(dir), INFO.file_stem, extension)?;
+        self.render(format, &mut out)?;
+        out.flush()?;
+        Ok(Some(path))
+    }
+
+    /// Renders the results in `format` into `out`.
+    pub fn render(&self, format: &SearchFormat, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        match format {
+            SearchFormat::Text => self.write_text(out)?,
+            SearchFormat::Json | SearchFormat::Jsonl => self.write_json(out, *format == SearchFormat::Jsonl)?,
+            SearchFormat::Csv => Report::from(self).render(&OutputFormat::Csv, out)?,
+            SearchFormat::Tsv => Report::from(self).render(&OutputFormat::Tsv, out)?,
+            SearchFormat::Markdown => Report::from(self).render(&OutputFormat::Markdown, out)?,
+        }
+        Ok(())
+    }
+
+    fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (n, hit) in self.hits.iter().enumerate() {
+            if n > 0 {
+                writeln!(out, "--")?;
+            }
+            for m in &hit.before {
+                writeln!(out, "  {}", text_line(m))?;
+            }
+            writeln!(out, "> {}", text_line(&hit.message))?;
+            for m in &hit.after {
+                writeln!(out, "  {}", text_line(m))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The shared JSON document shape (`behavior`, `schema_version`, `summary`,
+    /// `rows`), or one tagged hit per line for JSON Lines.
+    fn write_json(&self, out: &mut dyn Write, lines: bool) -> Result<(), Box<dyn Error>> {
+        let views: Vec<HitView> = self
+            .hits
+            .iter()
+            .map(|hit| HitView {
+                message: (&hit.message).into(),
+                before: hit.before.iter().map(MessageView::from).collect(),
+                after: hit.after.iter().map(MessageView::from).collect(),
+            })
+            .collect();
+        if lines {
+            for view in &views {
+                let line = json!({ "behavior": "search", "schema_version": INFO.schema_version, "hit": view });
+                serde_json::to_writer(&mut *out, &line)?;
+                writeln!(out)?;
+            }
+            return Ok(());
+        }
+        let document = json!({
+            "behavior": "search",
+            "schema_version": INFO.schema_version,
+            "summary": { "query": self.query, "matches": self.hits.len(), "searched": self.searched },
+            "rows": views,
+        });
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+/// Searches `messages` for `query`: every message that passes `filter` and
+/// matches is a hit, with up to `options.context` messages of the same chat
+/// before and after it (whether or not they pass the filter). Fails on an
+/// invalid regex.
+pub fn search(
+    messages: &[Message],
+    filter: &MessageFilter,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<SearchResults, Box<dyn Error>> {
+    let re = matcher(query, options)?;
+    Ok(SearchResults {
+        query: query.to_string(),
+        hits: find_hits(messages, filter, &re, options.context),
+        searched: messages.len(),
+    })
+}
+
+fn matcher(query: &str, options: &SearchOptions) -> Result<Regex, Box<dyn Error>> {
+    let pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let re = RegexBuilder::new(&pattern)
+        .case_insensitive(options.ignore_case)
+        .build()?;
+    Ok(re)
+}
+
+/// Finds all hits. Context never crosses a chat boundary.
+fn find_hits(messages: &[Message], filter: &MessageFilter, re: &Regex, context: usize) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    let mut start = 0;
+    while start < messages.len() {
+        // Messages come chat by chat; find the end of the current chat run.
+        let chat = &messages[start].chat;
+        let end = messages[start..]
+            .iter()
+            .position(|m| !std::sync::Arc::ptr_eq(&m.chat, chat))
+            .map_or(messages.len(), |offset| start + offset);
+        let run = &messages[start..end];
+
+        for (i, m) in run.iter().enumerate() {
+            if !filter.matches(m) || !re.is_match(&m.text) {
+                continue;
+            }
+            let from = i.saturating_sub(context);
+            let to = (i + 1 + context).min(run.len());
+            hits.push(Hit {
+                before: run[from..i].to_vec(),
+                message: m.clone(),
+                after: run[i + 1..to].to_vec(),
+            });
+        }
+        start = end;
+    }
+    hits
+}
+
+/// One row per message: the hit number, its role (`before`, `match`, `after`) and the message.
+impl From<&SearchResults> for Report {
+    fn from(results: &SearchResults) -> Self {
+        let mut rows = Vec::new();
+        for (n, hit) in results.hits.iter().enumerate() {
+            let messages = hit
+                .before
+                .iter()
+                .map(|m| ("before", m))
+                .chain(std::iter::once(("match", &hit.message)))
+                .chain(hit.after.iter().map(|m| ("after", m)));
+            for (role, m) in messages {
+                rows.push(vec![
+                    (n + 1).into(),
+                    role.into(),
+                    m.chat.id.into(),
+                    m.chat.name.as_deref().into(),
+                    m.id.into(),
+                    m.date.into(),
+                    m.from.as_deref().into(),
+                    m.from_id.as_deref().into(),
+                    Cell::from(m.text.as_str()),
+                ]);
+            }
+        }
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows,
+            summary: vec![
+                ("query", results.query.as_str().into()),
+                ("matches", results.hits.len().into()),
+                ("searched", results.searched.into()),
+            ],
+            summary_line: format!("→ Search: {} matches in {} messages", results.hits.len(), results.searched),
+        }
+    }
+}
+
+/// `[chat] #id date sender: text`, with line breaks flattened.
+fn text_line(m: &Message) -> String {
+    let date = m
+        .date
+        .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string());
+    format!(
+        "[{}] #{} {} {}: {}",
+        m.chat.label(),
+        m.id,
+        date,
+        m.sender_label(),
+        m.text.replace('\n', " ")
+    )
+}
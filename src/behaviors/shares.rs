@@ -1,24 +1,156 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::Message;
+use crate::report::{Cell, Column, ColumnType, Report};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::error::Error;
 
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "shares",
+    description: "From JSON, get who sent which link and who sent which media",
+    behavior_type: BehaviorType::Shares,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "shares",
+    columns: &[
+        Column::new("user_id", ColumnType::Text),
+        Column::new("name", ColumnType::Text),
+        Column::new("kind", ColumnType::Category),
+        Column::new("value", ColumnType::Text),
+        Column::new("message_id", ColumnType::Integer),
+        Column::new("date", ColumnType::Timestamp),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(Shares::new(args.json.clone())))
+}
+
+/// Shares behavior: lists who sent which link and who sent which media, one
+/// row per share (`user_id`, `name`, `kind`, `value`, `message_id`, `date`).
 pub struct Shares {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
 }
 
 impl Shares {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+        }
     }
 }
 
 impl Behavior for Shares {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::Shares
+    type Output = ShareList;
+
+    fn run(&self, ctx: &Context) -> Result<ShareList, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(shares(&messages))
+    }
+}
+
+/// What was shared: a link, or a media file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareKind {
+    Link,
+    Media,
+}
+
+impl ShareKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareKind::Link => "link",
+            ShareKind::Media => "media",
+        }
+    }
+}
+
+/// Every share, as listed by `shares`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShareList {
+    /// In message order; a message's links come before its media.
+    pub shares: Vec<Share>,
+    pub links: usize,
+    pub media: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Share {
+    pub user_id: Option<String>,
+    pub name: Option<String>,
+    pub kind: ShareKind,
+    /// The URL, or the attachment (see `media_name`).
+    pub value: String,
+    pub message_id: i64,
+    pub date: Option<DateTime<Utc>>,
+}
+
+/// Lists who sent which link and which media in `messages`.
+pub fn shares(messages: &[Message]) -> ShareList {
+    let mut shares = Vec::new();
+    let mut links = 0;
+    for msg in messages {
+        let share = |kind: ShareKind, value: String| Share {
+            user_id: msg.from_id.clone(),
+            name: msg.from.clone(),
+            kind,
+            value,
+            message_id: msg.id,
+            date: msg.date,
+        };
+        for url in msg.links() {
+            shares.push(share(ShareKind::Link, url));
+            links += 1;
+        }
+        if let Some(file) = media_name(msg) {
+            shares.push(share(ShareKind::Media, file.to_string()));
+        }
+    }
+    ShareList {
+        media: shares.len() - links,
+        shares,
+        links,
     }
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, for each message extract who sent which link & who sent which media
-        println!("Shares: would list user→media and user→link in \"{}\"...", self.json_path);
-        Ok(())
+impl From<&ShareList> for Report {
+    fn from(result: &ShareList) -> Self {
+        let rows: Vec<Vec<Cell>> = result
+            .shares
+            .iter()
+            .map(|s| {
+                vec![
+                    s.user_id.clone().into(),
+                    s.name.clone().into(),
+                    s.kind.as_str().into(),
+                    s.value.as_str().into(),
+                    s.message_id.into(),
+                    s.date.into(),
+                ]
+            })
+            .collect();
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows,
+            summary: vec![("links", result.links.into()), ("media", result.media.into())],
+            summary_line: format!("→ Shares: {} links and {} media shared", result.links, result.media),
+        }
     }
 }
+
+/// The attachment's path in the export (`file` or `photo`), or its media type
+/// when the file itself wasn't exported.
+pub fn media_name(msg: &Message) -> Option<&str> {
+    msg.file
+        .as_deref()
+        .or(msg.photo.as_deref())
+        .or(msg.media_type.as_deref())
+}
@@ -1,32 +1,221 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorOption, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::{tokenize, Message};
+use crate::report::{Cell, Column, ColumnType, Report};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::error::Error;
 
+/// Default words per minute used for the reading-time estimate.
+const READING_WPM: f64 = 200.0;
+/// Default number of most frequent words to list.
+const TOP_WORDS: usize = 20;
+
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "text-stats",
+    description: "From JSON, get number of words, characters, sentences, paragraphs, reading_time, word_counter, average word length, num messages with media",
+    behavior_type: BehaviorType::TextStats,
+    inputs: &[Input::Json],
+    options: &[
+        BehaviorOption {
+            name: "top-words",
+            help: "How many of the most frequent words to list (default 20)",
+        },
+        BehaviorOption {
+            name: "reading-wpm",
+            help: "Words per minute of the reading-time estimate (default 200)",
+        },
+    ],
+    file_stem: "text_stats",
+    columns: &[
+        Column::new("metric", ColumnType::Category),
+        Column::new("value", ColumnType::Float),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    let mut b = TextStats::new(args.json.clone());
+    if let Some(n) = args.option("top-words")? {
+        b.top_words = n;
+    }
+    if let Some(wpm) = args.option("reading-wpm")? {
+        b.reading_wpm = wpm;
+    }
+    Ok(Box::new(b))
+}
+
+/// TextStats behavior: over all (filtered) message texts, computes
+///
+///   - total words, characters, sentences and paragraphs,
+///   - estimated reading time (at `reading_wpm` words per minute, 200 by default),
+///   - average word length,
+///   - number of messages containing media,
+///   - the `top_words` most frequent words (word_counter, 20 by default).
+///
+/// Rows are `metric`/`value` pairs; word counts use the metric `word:<word>`.
 pub struct TextStats {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
+    pub top_words: usize,
+    pub reading_wpm: f64,
 }
 
 impl TextStats {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+            top_words: TOP_WORDS,
+            reading_wpm: READING_WPM,
+        }
     }
 }
 
 impl Behavior for TextStats {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::TextStats
-    }
-
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, compute:
-        //   - total words
-        //   - total characters
-        //   - number of sentences
-        //   - number of paragraphs
-        //   - estimated reading_time
-        //   - word_counter
-        //   - average word length
-        //   - number of messages containing media
-        println!("TextStats: would compute text metrics in \"{}\"...", self.json_path);
-        Ok(())
+    type Output = TextStatistics;
+
+    fn run(&self, ctx: &Context) -> Result<TextStatistics, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(text_stats(&messages, self.top_words, self.reading_wpm))
+    }
+}
+
+/// Text statistics, as computed by `text_stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TextStatistics {
+    pub messages: usize,
+    pub words: usize,
+    pub characters: usize,
+    pub sentences: usize,
+    pub paragraphs: usize,
+    /// Rounded to one decimal.
+    pub reading_time_minutes: f64,
+    /// Rounded to two decimals.
+    pub average_word_length: f64,
+    pub messages_with_media: usize,
+    /// The most frequent words with their counts, most frequent first, then alphabetically.
+    pub top_words: Vec<(String, usize)>,
+}
+
+/// Running totals of `text_stats`, one per chunk of messages.
+#[derive(Default)]
+struct Tally {
+    words: usize,
+    characters: usize,
+    sentences: usize,
+    paragraphs: usize,
+    word_chars: usize,
+    media: usize,
+    word_counter: HashMap<String, usize>,
+}
+
+impl Tally {
+    fn add(mut self, msg: &Message) -> Self {
+        let text = &msg.text;
+        self.words += text.split_whitespace().count();
+        self.characters += text.chars().count();
+        self.sentences += text
+            .split(['.', '!', '?'])
+            .filter(|s| s.chars().any(char::is_alphanumeric))
+            .count();
+        self.paragraphs += text
+            .split("\n\n")
+            .filter(|p| !p.trim().is_empty())
+            .count();
+        for word in tokenize(text) {
+            self.word_chars += word.chars().count();
+            *self.word_counter.entry(word).or_default() += 1;
+        }
+        if msg.has_media() {
+            self.media += 1;
+        }
+        self
+    }
+
+    fn merge(mut self, other: Tally) -> Self {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.sentences += other.sentences;
+        self.paragraphs += other.paragraphs;
+        self.word_chars += other.word_chars;
+        self.media += other.media;
+        for (word, n) in other.word_counter {
+            *self.word_counter.entry(word).or_default() += n;
+        }
+        self
+    }
+}
+
+/// Computes the text statistics of `messages`, listing the `top_words` most
+/// frequent words and estimating the reading time at `reading_wpm` words per minute.
+/// Chunks of messages are tallied in parallel.
+pub fn text_stats(messages: &[Message], top_words: usize, reading_wpm: f64) -> TextStatistics {
+    let Tally {
+        words,
+        characters,
+        sentences,
+        paragraphs,
+        word_chars,
+        media,
+        word_counter,
+    } = messages.par_iter().fold(Tally::default, Tally::add).reduce(Tally::default, Tally::merge);
+
+    let counted_words: usize = word_counter.values().sum();
+    let average_word_length = if counted_words == 0 {
+        0.0
+    } else {
+        word_chars as f64 / counted_words as f64
+    };
+    let mut top: Vec<(String, usize)> = word_counter.into_iter().collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(top_words);
+
+    let reading_time = words as f64 / reading_wpm;
+    TextStatistics {
+        messages: messages.len(),
+        words,
+        characters,
+        sentences,
+        paragraphs,
+        reading_time_minutes: (reading_time * 10.0).round() / 10.0,
+        average_word_length: (average_word_length * 100.0).round() / 100.0,
+        messages_with_media: media,
+        top_words: top,
+    }
+}
+
+impl From<&TextStatistics> for Report {
+    fn from(stats: &TextStatistics) -> Self {
+        let summary: Vec<(&str, Cell)> = vec![
+            ("words", stats.words.into()),
+            ("characters", stats.characters.into()),
+            ("sentences", stats.sentences.into()),
+            ("paragraphs", stats.paragraphs.into()),
+            ("reading_time_minutes", stats.reading_time_minutes.into()),
+            ("average_word_length", stats.average_word_length.into()),
+            ("messages_with_media", stats.messages_with_media.into()),
+        ];
+        let mut rows: Vec<Vec<Cell>> = summary
+            .iter()
+            .map(|(metric, value)| vec![(*metric).into(), value.clone()])
+            .collect();
+        rows.extend(
+            stats
+                .top_words
+                .iter()
+                .map(|(word, count)| vec![format!("word:{}", word).into(), (*count).into()]),
+        );
+
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            rows,
+            summary,
+            summary_line: format!("→ TextStats: Analyzed {} messages", stats.messages),
+        }
     }
 }
@@ -1,31 +1,209 @@
-use crate::behavior::{Behavior, BehaviorType};
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior, Input};
+use crate::context::Context;
+use crate::model::Message;
+use crate::report::{Cell, Column, ColumnType, Report};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 
+/// Registry entry (see `BehaviorInfo`).
+pub const INFO: BehaviorInfo = BehaviorInfo {
+    name: "user-interactions",
+    description: "From JSON, get all interactions of each user and print id, num_messages, media & links shared, unique links, name/username changes, last_seen",
+    behavior_type: BehaviorType::UserInteractions,
+    inputs: &[Input::Json],
+    options: &[],
+    file_stem: "user_interactions",
+    columns: &[
+        Column::new("user_id", ColumnType::Text),
+        Column::new("name", ColumnType::Text),
+        Column::new("messages", ColumnType::Integer),
+        Column::new("media", ColumnType::Integer),
+        Column::new("links", ColumnType::Integer),
+        Column::new("unique_links", ColumnType::Integer),
+        Column::new("name_changes", ColumnType::Integer),
+        Column::new("last_seen", ColumnType::Timestamp),
+    ],
+    schema_version: 1,
+    build: Some(build),
+};
+
+fn build(_: &BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    Ok(Box::new(UserInteractions::new(args.json.clone())))
+}
+
+/// UserInteractions behavior: for each sender (keyed by `from_id`) collects
+///
+///   - num_messages, num_media, num_links and num_unique_links,
+///   - name_changes: how often the display name differs from the one on the
+///     sender's previous message (exports carry no usernames, so username
+///     changes can't be tracked),
+///   - last_seen: date of the sender's latest message.
+///
+/// One row per user, most active first.
 pub struct UserInteractions {
-    pub json_path: String,
+    pub json_paths: Vec<String>,
 }
 
 impl UserInteractions {
-    pub fn new(json_path: String) -> Self {
-        Self { json_path }
+    pub fn new(json_paths: Vec<String>) -> Self {
+        Self {
+            json_paths,
+        }
     }
 }
 
 impl Behavior for UserInteractions {
-    fn behavior_type(&self) -> BehaviorType {
-        BehaviorType::UserInteractions
+    type Output = UserInteractionStats;
+
+    fn run(&self, ctx: &Context) -> Result<UserInteractionStats, Box<dyn Error>> {
+        let messages = ctx.messages(&self.json_paths)?;
+        Ok(user_interactions(&messages))
+    }
+}
+
+/// Per-sender activity, as computed by `user_interactions`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserInteractionStats {
+    /// Most active first, then by id.
+    pub users: Vec<UserActivity>,
+    /// Messages looked at, including those without a sender.
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserActivity {
+    pub user_id: String,
+    /// Display name on the sender's latest message.
+    pub name: Option<String>,
+    pub messages: usize,
+    pub media: usize,
+    pub links: usize,
+    pub unique_links: usize,
+    pub name_changes: usize,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Tally<'a> {
+    /// The display names on the sender's first and latest message (of a chunk).
+    first_name: Option<&'a str>,
+    name: Option<&'a str>,
+    messages: usize,
+    media: usize,
+    links: usize,
+    unique_links: HashSet<String>,
+    name_changes: usize,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+impl<'a> Tally<'a> {
+    fn add(&mut self, msg: &'a Message) {
+        if let Some(name) = msg.from.as_deref() {
+            if self.name.is_some_and(|previous| previous != name) {
+                self.name_changes += 1;
+            }
+            self.first_name = self.first_name.or(Some(name));
+            self.name = Some(name);
+        }
+        self.messages += 1;
+        if msg.has_media() {
+            self.media += 1;
+        }
+        let links = msg.links();
+        self.links += links.len();
+        self.unique_links.extend(links);
+        if msg.date > self.last_seen {
+            self.last_seen = msg.date;
+        }
+    }
+
+    /// Adds the tally of the chunk that follows this one.
+    fn merge(&mut self, next: Tally<'a>) {
+        if let (Some(previous), Some(name)) = (self.name, next.first_name) {
+            if previous != name {
+                self.name_changes += 1;
+            }
+        }
+        self.first_name = self.first_name.or(next.first_name);
+        self.name = next.name.or(self.name);
+        self.messages += next.messages;
+        self.media += next.media;
+        self.links += next.links;
+        self.unique_links.extend(next.unique_links);
+        self.name_changes += next.name_changes;
+        self.last_seen = self.last_seen.max(next.last_seen);
     }
+}
+
+/// Aggregates `messages` per sender (`from_id`); messages without one are skipped.
+/// Chunks of messages are aggregated in parallel, then merged in order.
+pub fn user_interactions(messages: &[Message]) -> UserInteractionStats {
+    // 1) Aggregate per sender
+    let users = messages
+        .par_iter()
+        .fold(HashMap::new, |mut users: HashMap<&str, Tally>, msg| {
+            if let Some(id) = msg.from_id.as_deref() {
+                users.entry(id).or_default().add(msg);
+            }
+            users
+        })
+        .reduce(HashMap::new, |mut users, next| {
+            for (id, tally) in next {
+                users.entry(id).or_default().merge(tally);
+            }
+            users
+        });
+
+    // 2) Most active first, then by id
+    let mut users: Vec<UserActivity> = users
+        .into_iter()
+        .map(|(id, u)| UserActivity {
+            user_id: id.to_string(),
+            name: u.name.map(str::to_string),
+            messages: u.messages,
+            media: u.media,
+            links: u.links,
+            unique_links: u.unique_links.len(),
+            name_changes: u.name_changes,
+            last_seen: u.last_seen,
+        })
+        .collect();
+    users.sort_by(|a, b| b.messages.cmp(&a.messages).then_with(|| a.user_id.cmp(&b.user_id)));
+    UserInteractionStats {
+        users,
+        messages: messages.len(),
+    }
+}
 
-    fn run(&self) -> Result<(), Box<dyn Error>> {
-        // TODO: open self.json_path, parse messages, for each user ID collect:
-        //  - num_messages
-        //  - num_media
-        //  - num_links
-        //  - num_unique_links
-        //  - name‐change count
-        //  - username‐change count
-        //  - last_seen
-        println!("UserInteractions: would analyze interactions in \"{}\"...", self.json_path);
-        Ok(())
+impl From<&UserInteractionStats> for Report {
+    fn from(result: &UserInteractionStats) -> Self {
+        let rows: Vec<Vec<Cell>> = result
+            .users
+            .iter()
+            .map(|u| {
+                vec![
+                    u.user_id.as_str().into(),
+                    u.name.clone().into(),
+                    u.messages.into(),
+                    u.media.into(),
+                    u.links.into(),
+                    u.unique_links.into(),
+                    u.name_changes.into(),
+                    u.last_seen.into(),
+                ]
+            })
+            .collect();
+        Report {
+            behavior: INFO.name,
+            file_stem: INFO.file_stem,
+            schema_version: INFO.schema_version,
+            columns: INFO.columns.to_vec(),
+            summary: vec![("users", rows.len().into()), ("messages", result.messages.into())],
+            summary_line: format!("→ UserInteractions: {} users", rows.len()),
+            rows,
+        }
     }
 }
@@ -0,0 +1,416 @@
+// src/charts.rs
+
+use crate::common::ChartFormat;
+use crate::error::AnalyzerError;
+use crate::html::escape;
+use crate::model::Message;
+use crate::report::create_output;
+use chrono::{DateTime, Datelike, NaiveDate, Timelike};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::PathBuf;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 320.0;
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_RIGHT: f64 = 16.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 56.0;
+const BAR_COLOR: &str = "#486581";
+const SERIES_COLORS: [&str; 6] = ["#486581", "#f0b429", "#3ebd93", "#ef4e4e", "#9446ed", "#9fb3c8"];
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Senders shown in the stacked chart; everyone else is folded into "others".
+const TOP_USERS: usize = 5;
+
+/// Past this many days, the stacked chart counts per month instead of per day.
+const MAX_DAILY_BARS: i64 = 92;
+
+/// A rendered chart: `name` is its file stem (`<name>.svg`), `svg` a standalone SVG document.
+pub struct Chart {
+    pub name: &'static str,
+    pub title: String,
+    pub svg: String,
+}
+
+/// The time-based charts of a set of (filtered) messages, in `timezone`:
+///
+///   - `messages_per_slot`: bar chart of the 48 half-hour slots (as count-time-slots),
+///   - `messages_per_day`: line chart of messages per day (as count-daily), gaps filled with 0,
+///   - `weekday_hour_heatmap`: messages per weekday × hour,
+///   - `top_users_per_day`: messages per day (per month for long spans) stacked by
+///     the five most active senders, everyone else as "others".
+///
+/// Messages without a date are left out.
+pub fn activity_charts(messages: &[Message], timezone: Tz) -> Vec<Chart> {
+    let dated: Vec<(&Message, DateTime<Tz>)> = messages
+        .iter()
+        .filter_map(|m| Some((m, m.date?.with_timezone(&timezone))))
+        .collect();
+
+    // 1) Half-hour slots
+    let mut slots = [0.0; 48];
+    for (_, date) in &dated {
+        slots[(date.hour() * 2 + date.minute() / 30) as usize] += 1.0;
+    }
+    let slot_labels: Vec<String> = (0..48)
+        .map(|n| format!("{:02}:{:02}", n / 2, if n % 2 == 0 { "00" } else { "30" }))
+        .collect();
+
+    // 2) Days, gaps included
+    let mut per_day: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for (_, date) in &dated {
+        *per_day.entry(date.date_naive()).or_default() += 1.0;
+    }
+    let days: Vec<NaiveDate> = match (per_day.keys().next(), per_day.keys().next_back()) {
+        (Some(&first), Some(&last)) => first.iter_days().take_while(|d| *d <= last).collect(),
+        _ => Vec::new(),
+    };
+    let day_labels: Vec<String> = days.iter().map(|d| d.to_string()).collect();
+    let day_values: Vec<f64> = days.iter().map(|d| per_day.get(d).copied().unwrap_or(0.0)).collect();
+
+    // 3) Weekday × hour
+    let mut grid = [[0.0; 24]; 7];
+    for (_, date) in &dated {
+        grid[date.weekday().num_days_from_monday() as usize][date.hour() as usize] += 1.0;
+    }
+
+    // 4) Top senders per day (or month)
+    let mut per_user: HashMap<&str, (usize, &str)> = HashMap::new();
+    for (msg, _) in &dated {
+        if let Some(id) = msg.from_id.as_deref() {
+            let entry = per_user.entry(id).or_insert((0, msg.sender_label()));
+            entry.0 += 1;
+            entry.1 = msg.sender_label();
+        }
+    }
+    let mut ranked: Vec<(&str, (usize, &str))> = per_user.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(b.0)));
+    ranked.truncate(TOP_USERS);
+
+    let monthly = days.len() as i64 > MAX_DAILY_BARS;
+    let bucket = |d: NaiveDate| if monthly { d.with_day(1).unwrap() } else { d };
+    let buckets: Vec<NaiveDate> = {
+        let mut b: Vec<NaiveDate> = days.iter().map(|d| bucket(*d)).collect();
+        b.dedup();
+        b
+    };
+    let bucket_index: HashMap<NaiveDate, usize> = buckets.iter().enumerate().map(|(i, d)| (*d, i)).collect();
+    let mut series: Vec<(String, Vec<f64>)> = ranked
+        .iter()
+        .map(|(_, (_, name))| (name.to_string(), vec![0.0; buckets.len()]))
+        .collect();
+    series.push(("others".to_string(), vec![0.0; buckets.len()]));
+    for (msg, date) in &dated {
+        let slot = bucket_index[&bucket(date.date_naive())];
+        let row = msg
+            .from_id
+            .as_deref()
+            .and_then(|id| ranked.iter().position(|(r, _)| *r == id))
+            .unwrap_or(ranked.len());
+        series[row].1[slot] += 1.0;
+    }
+    if series.last().is_some_and(|(_, v)| v.iter().all(|x| *x == 0.0)) {
+        series.pop();
+    }
+    let bucket_labels: Vec<String> = buckets
+        .iter()
+        .map(|d| if monthly { d.format("%Y-%m").to_string() } else { d.to_string() })
+        .collect();
+
+    let per = if monthly { "month" } else { "day" };
+    let slot_title = format!("Messages per 30-minute slot ({})", timezone.name());
+    let heatmap_title = format!("Messages per weekday and hour ({})", timezone.name());
+    vec![
+        Chart {
+            name: "messages_per_slot",
+            svg: bar_chart(&slot_title, &slot_labels, &slots),
+            title: slot_title,
+        },
+        Chart {
+            name: "messages_per_day",
+            title: "Messages per day".to_string(),
+            svg: line_chart("Messages per day", &day_labels, &day_values),
+        },
+        Chart {
+            name: "weekday_hour_heatmap",
+            svg: heatmap(&heatmap_title, &grid),
+            title: heatmap_title,
+        },
+        Chart {
+            name: "top_users_per_day",
+            title: format!("Messages per {} by top senders", per),
+            svg: stacked_bar_chart(&format!("Messages per {} by top senders", per), &bucket_labels, &series),
+        },
+    ]
+}
+
+/// Writes each chart into `output_dir` as `<name>.svg` or `<name>.png`.
+pub fn write_charts(charts: &[Chart], format: ChartFormat, output_dir: &str) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut paths = Vec::new();
+    for chart in charts {
+        let (bytes, extension) = match format {
+            ChartFormat::Svg => (chart.svg.clone().into_bytes(), "svg"),
+            ChartFormat::Png => (to_png(&chart.svg)?, "png"),
+        };
+        let (path, mut out) = create_output(Some(output_dir), chart.name, extension)?;
+        out.write_all(&bytes).map_err(AnalyzerError::write(&path))?;
+        out.flush().map_err(AnalyzerError::write(&path))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Rasterizes a chart's SVG to PNG (system fonts are used for the labels).
+#[cfg(feature = "png")]
+pub fn to_png(svg: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    use resvg::{tiny_skia, usvg};
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Chart has an empty size")?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap.encode_png()?)
+}
+
+#[cfg(not(feature = "png"))]
+pub fn to_png(_svg: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err("PNG charts need a build with the `png` feature (cargo build --features png)".into())
+}
+
+/// Bars over a categorical x axis.
+pub fn bar_chart(title: &str, labels: &[String], values: &[f64]) -> String {
+    let max = nice_max(values.iter().cloned().fold(0.0, f64::max));
+    let mut svg = open_svg(title);
+    y_axis(&mut svg, max);
+    let slot = plot_width() / values.len().max(1) as f64;
+    for (n, (label, value)) in labels.iter().zip(values).enumerate() {
+        let h = value / max * plot_height();
+        let _ = write!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}: {}</title></rect>"#,
+            MARGIN_LEFT + n as f64 * slot + slot * 0.1,
+            MARGIN_TOP + plot_height() - h,
+            slot * 0.8,
+            h,
+            BAR_COLOR,
+            escape(label),
+            value
+        );
+    }
+    x_labels(&mut svg, labels);
+    close_svg(svg)
+}
+
+/// A line with a point per value over a categorical x axis.
+pub fn line_chart(title: &str, labels: &[String], values: &[f64]) -> String {
+    let max = nice_max(values.iter().cloned().fold(0.0, f64::max));
+    let mut svg = open_svg(title);
+    y_axis(&mut svg, max);
+    let step = plot_width() / values.len().max(1) as f64;
+    let points: Vec<(f64, f64)> = values
+        .iter()
+        .enumerate()
+        .map(|(n, v)| {
+            (
+                MARGIN_LEFT + (n as f64 + 0.5) * step,
+                MARGIN_TOP + plot_height() - v / max * plot_height(),
+            )
+        })
+        .collect();
+    let path: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+    let _ = write!(
+        svg,
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+        path.join(" "),
+        BAR_COLOR
+    );
+    if points.len() <= 120 {
+        for ((x, y), (label, value)) in points.iter().zip(labels.iter().zip(values)) {
+            let _ = write!(
+                svg,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="{}"><title>{}: {}</title></circle>"#,
+                x,
+                y,
+                BAR_COLOR,
+                escape(label),
+                value
+            );
+        }
+    }
+    x_labels(&mut svg, labels);
+    close_svg(svg)
+}
+
+/// A 7 × 24 grid (Monday first), shaded by count.
+pub fn heatmap(title: &str, grid: &[[f64; 24]; 7]) -> String {
+    let max = grid.iter().flatten().cloned().fold(0.0, f64::max);
+    let mut svg = open_svg(title);
+    let cell_w = plot_width() / 24.0;
+    let cell_h = plot_height() / 7.0;
+    for (day, row) in grid.iter().enumerate() {
+        let y = MARGIN_TOP + day as f64 * cell_h;
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+            MARGIN_LEFT - 6.0,
+            y + cell_h / 2.0,
+            WEEKDAYS[day]
+        );
+        for (hour, value) in row.iter().enumerate() {
+            let intensity = if max > 0.0 { value / max } else { 0.0 };
+            let _ = write!(
+                svg,
+                r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="#486581" fill-opacity="{:.3}" stroke="#fff"><title>{} {:02}:00: {}</title></rect>"##,
+                MARGIN_LEFT + hour as f64 * cell_w,
+                y,
+                cell_w,
+                cell_h,
+                0.05 + 0.95 * intensity,
+                WEEKDAYS[day],
+                hour,
+                value
+            );
+        }
+    }
+    for hour in (0..24).step_by(2) {
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{:02}</text>"#,
+            MARGIN_LEFT + (hour as f64 + 0.5) * cell_w,
+            MARGIN_TOP + plot_height() + 16.0,
+            hour
+        );
+    }
+    close_svg(svg)
+}
+
+/// Bars over a categorical x axis, stacked by series, with a legend.
+pub fn stacked_bar_chart(title: &str, labels: &[String], series: &[(String, Vec<f64>)]) -> String {
+    let totals: Vec<f64> = (0..labels.len())
+        .map(|n| series.iter().map(|(_, values)| values[n]).sum())
+        .collect();
+    let max = nice_max(totals.iter().cloned().fold(0.0, f64::max));
+    let mut svg = open_svg(title);
+    y_axis(&mut svg, max);
+    let slot = plot_width() / labels.len().max(1) as f64;
+    for (n, label) in labels.iter().enumerate() {
+        let mut top = MARGIN_TOP + plot_height();
+        for (s, (name, values)) in series.iter().enumerate() {
+            let h = values[n] / max * plot_height();
+            if h <= 0.0 {
+                continue;
+            }
+            top -= h;
+            let _ = write!(
+                svg,
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{} – {}: {}</title></rect>"#,
+                MARGIN_LEFT + n as f64 * slot + slot * 0.1,
+                top,
+                slot * 0.8,
+                h,
+                SERIES_COLORS[s % SERIES_COLORS.len()],
+                escape(label),
+                escape(name),
+                values[n]
+            );
+        }
+    }
+    x_labels(&mut svg, labels);
+
+    // Legend, top right
+    let mut x = WIDTH - MARGIN_RIGHT;
+    for (s, (name, _)) in series.iter().enumerate().rev() {
+        let name: String = name.chars().take(18).collect();
+        x -= 20.0 + name.chars().count() as f64 * 6.5;
+        let _ = write!(
+            svg,
+            r#"<rect x="{:.1}" y="24" width="10" height="10" fill="{}"/><text x="{:.1}" y="33">{}</text>"#,
+            x,
+            SERIES_COLORS[s % SERIES_COLORS.len()],
+            x + 14.0,
+            escape(&name)
+        );
+    }
+    close_svg(svg)
+}
+
+fn plot_width() -> f64 {
+    WIDTH - MARGIN_LEFT - MARGIN_RIGHT
+}
+
+fn plot_height() -> f64 {
+    HEIGHT - MARGIN_TOP - MARGIN_BOTTOM
+}
+
+fn open_svg(title: &str) -> String {
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="DejaVu Sans, Helvetica, Arial, sans-serif" font-size="11" fill="#334e68"><title>{t}</title><rect width="100%" height="100%" fill="#fff"/><text x="{x}" y="20" font-size="14" font-weight="bold">{t}</text>"##,
+        w = WIDTH,
+        h = HEIGHT,
+        x = MARGIN_LEFT,
+        t = escape(title)
+    )
+}
+
+fn close_svg(mut svg: String) -> String {
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Gridlines and labels at 0, ¼, ½, ¾ and all of `max`.
+fn y_axis(svg: &mut String, max: f64) {
+    for n in 0..=4 {
+        let value = max * n as f64 / 4.0;
+        let y = MARGIN_TOP + plot_height() - plot_height() * n as f64 / 4.0;
+        let _ = write!(
+            svg,
+            r##"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="#d9e2ec"/><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"##,
+            MARGIN_LEFT,
+            WIDTH - MARGIN_RIGHT,
+            MARGIN_LEFT - 6.0,
+            y + 4.0,
+            format_tick(value)
+        );
+    }
+}
+
+/// Rotated labels under the plot, thinned out to at most ~16.
+fn x_labels(svg: &mut String, labels: &[String]) {
+    let step = labels.len().div_ceil(16).max(1);
+    let slot = plot_width() / labels.len().max(1) as f64;
+    for (n, label) in labels.iter().enumerate().step_by(step) {
+        let x = MARGIN_LEFT + (n as f64 + 0.5) * slot;
+        let y = MARGIN_TOP + plot_height() + 12.0;
+        let _ = write!(
+            svg,
+            r#"<text x="{x:.1}" y="{y:.1}" text-anchor="end" transform="rotate(-35 {x:.1} {y:.1})">{}</text>"#,
+            escape(label)
+        );
+    }
+}
+
+/// Top of the y axis: four times a round tick step (1, 2, 2.5 or 5 × 10ⁿ, at least 1).
+fn nice_max(max: f64) -> f64 {
+    let raw = (max / 4.0).max(1.0);
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 2.5, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|v| *v >= raw)
+        .unwrap_or(raw);
+    step * 4.0
+}
+
+fn format_tick(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
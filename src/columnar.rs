@@ -0,0 +1,147 @@
+// src/columnar.rs
+
+use crate::common::OutputFormat;
+use crate::report::{Cell, ColumnType, Report};
+use arrow::array::{
+    ArrayRef, Date32Builder, Float64Builder, Int64Builder, StringBuilder, StringDictionaryBuilder,
+    TimestampMicrosecondBuilder,
+};
+use arrow::datatypes::{Field, Int32Type, Schema};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::error::Error;
+use std::sync::Arc;
+
+/// Encodes a report as a Parquet file or an Arrow IPC file (Feather v2).
+///
+/// Column types map as follows: `Integer` → Int64, `Float` → Float64,
+/// `Text` → Utf8, `Category` → Dictionary(Int32, Utf8), `Date` → Date32,
+/// `Timestamp` → Timestamp(µs, UTC). The summary is stored in the file's
+/// key/value metadata as JSON under `summary`, next to `behavior` and `schema_version`.
+pub fn encode(report: &Report, format: &OutputFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+    let batch = record_batch(report)?;
+    let mut buffer = Vec::new();
+    match format {
+        OutputFormat::Parquet => {
+            let props = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(props))?;
+            writer.write(&batch)?;
+            writer.close()?;
+        }
+        OutputFormat::Arrow => {
+            let mut writer = FileWriter::try_new(&mut buffer, &batch.schema())?;
+            writer.write(&batch)?;
+            writer.finish()?;
+        }
+        _ => return Err(format!("{:?} is not a columnar format", format).into()),
+    }
+    Ok(buffer)
+}
+
+fn record_batch(report: &Report) -> Result<RecordBatch, Box<dyn Error>> {
+    let mut fields = Vec::with_capacity(report.columns.len());
+    let mut arrays = Vec::with_capacity(report.columns.len());
+    for (n, column) in report.columns.iter().enumerate() {
+        let cells = report.rows.iter().map(|row| row.get(n).unwrap_or(&Cell::Null));
+        let array = column_array(column.kind, cells).map_err(|cell| {
+            format!(
+                "Column \"{}\" of {} is {} but holds {:?}",
+                column.name,
+                report.behavior,
+                column.kind.as_str(),
+                cell
+            )
+        })?;
+        fields.push(Field::new(column.name, array.data_type().clone(), true));
+        arrays.push(array);
+    }
+
+    let metadata = [
+        ("behavior".to_string(), report.behavior.to_string()),
+        ("schema_version".to_string(), report.schema_version.to_string()),
+        ("summary".to_string(), report.summary_json().to_string()),
+    ];
+    let schema = Schema::new(fields).with_metadata(metadata.into_iter().collect());
+    Ok(RecordBatch::try_new(Arc::new(schema), arrays)?)
+}
+
+/// Builds one typed array; on a cell that doesn't fit the column type, returns that cell.
+fn column_array<'a>(kind: ColumnType, cells: impl Iterator<Item = &'a Cell>) -> Result<ArrayRef, Cell> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    let array: ArrayRef = match kind {
+        ColumnType::Integer => {
+            let mut builder = Int64Builder::new();
+            for cell in cells {
+                match cell {
+                    Cell::Null => builder.append_null(),
+                    Cell::Int(n) => builder.append_value(*n),
+                    other => return Err(other.clone()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Float => {
+            let mut builder = Float64Builder::new();
+            for cell in cells {
+                match cell {
+                    Cell::Null => builder.append_null(),
+                    Cell::Int(n) => builder.append_value(*n as f64),
+                    Cell::Float(x) => builder.append_value(*x),
+                    other => return Err(other.clone()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Text => {
+            let mut builder = StringBuilder::new();
+            for cell in cells {
+                match cell {
+                    Cell::Null => builder.append_null(),
+                    other => builder.append_value(other.to_text()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Category => {
+            let mut builder = StringDictionaryBuilder::<Int32Type>::new();
+            for cell in cells {
+                match cell {
+                    Cell::Null => builder.append_null(),
+                    other => {
+                        builder.append_value(other.to_text());
+                    }
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Date => {
+            let mut builder = Date32Builder::new();
+            for cell in cells {
+                match cell {
+                    Cell::Null => builder.append_null(),
+                    Cell::Date(d) => builder.append_value((*d - epoch).num_days() as i32),
+                    other => return Err(other.clone()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ColumnType::Timestamp => {
+            let mut builder = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+            for cell in cells {
+                match cell {
+                    Cell::Null => builder.append_null(),
+                    Cell::Time(t) => builder.append_value(t.timestamp_micros()),
+                    other => return Err(other.clone()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    };
+    Ok(array)
+}
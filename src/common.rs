@@ -1,12 +1,54 @@
 use clap::ValueEnum;
+use serde::Deserialize;
 
 /// Shared OutputFormat for all behaviors:
 ///   - `Stdout`: print to standard output (ignores `output_dir`),
 ///   - `Txt`: write a plain‐text file (`*.txt`) in `output_dir`,
-///   - `Csv`: write a CSV file (`*.csv`) in `output_dir`.
-#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+///   - `Csv`: write an RFC 4180 CSV file (`*.csv`) in `output_dir`,
+///   - `Tsv`: write a tab-separated file (`*.tsv`) in `output_dir`,
+///   - `Json`: write a JSON document (`*.json`) with a versioned schema in `output_dir`,
+///   - `Jsonl`: write JSON Lines (`*.jsonl`), one record per row, in `output_dir`,
+///   - `Markdown`: write a Markdown table (`*.md`) in `output_dir`,
+///   - `Parquet`: write a typed Parquet file (`*.parquet`) in `output_dir`,
+///   - `Arrow`: write a typed Arrow IPC / Feather v2 file (`*.arrow`) in `output_dir`.
+///
+/// `Parquet` and `Arrow` are only available when built with the `arrow` feature.
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     Stdout,
     Txt,
     Csv,
-}
\ No newline at end of file
+    Tsv,
+    Json,
+    Jsonl,
+    Markdown,
+    Parquet,
+    Arrow,
+}
+
+/// Combined report of an `all`/`group` run, written next to the per-behavior outputs:
+///   - `Html`: one self-contained page (`report.html`) with charts and tables,
+///   - `Markdown`: one Markdown document (`report.md`) built from a template.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+}
+
+/// Image format of the `charts` subcommand:
+///   - `Svg`: standalone SVG files (`*.svg`),
+///   - `Png`: rasterized PNG files (`*.png`), only available with the `png` feature.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum ChartFormat {
+    Svg,
+    Png,
+}
+
+/// `s` as a `&'static str`, for names only known at run time (a plugin's
+/// manifest, a script's summary keys) that fill the same fields as the
+/// built-in ones. Leaked once per name, for the life of the process.
+pub(crate) fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
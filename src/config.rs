@@ -0,0 +1,259 @@
+// src/config.rs
+
+use crate::common::{OutputFormat, ReportFormat};
+use crate::error::AnalyzerError;
+use crate::filter::{parse_since, parse_until, parse_where, MessageFilter};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Looked up in the current folder when no `--config` is given, before
+/// `$XDG_CONFIG_HOME/telegram-analyzer/config.toml` (`~/.config/...` without it).
+const LOCAL_CONFIG: &str = "telegram-analyzer.toml";
+
+/// Settings read from a TOML config file. Every key is optional and the
+/// command line wins over the file:
+///
+/// ```toml
+/// json = ["exports/result.json"]      # inputs of `all`/`group`
+/// folder = "exports/files"
+/// file = "exports/files/report.pdf"
+/// timezone = "Europe/Berlin"          # IANA name; buckets slots, days and charts
+/// strict = true                       # fail on malformed messages instead of skipping them
+/// threads = 8                         # worker threads (default: one per CPU core)
+/// pseudonymize = true                 # key from $TELEGRAM_ANALYZER_PSEUDONYM_KEY
+/// scrub-text = true                   # with pseudonymize: no phones, emails, mentions
+/// output = "out"
+/// format = "csv"
+/// report = "html"
+/// template = "report.md.tmpl"
+/// behaviors = ["count-daily", "text-stats"]   # what `all` runs (default: everything)
+///
+/// [filter]                            # same names as the filter options
+/// since = "2024-01-01"
+/// exclude-bots = true
+///
+/// [behavior.text-stats]               # per-behavior settings
+/// format = "json"
+/// top-words = 50
+///
+/// [profile.weekly-report]             # picked with --profile weekly-report
+/// output = "weekly"
+/// filter = { since = "2024-06-01" }
+/// ```
+///
+/// A profile's keys override the top-level ones (tables key by key).
+/// Relative paths are relative to the config file's folder.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub json: Vec<String>,
+    pub folder: Option<String>,
+    pub file: Option<String>,
+    pub timezone: Option<String>,
+    pub strict: bool,
+    pub threads: Option<u16>,
+    pub pseudonymize: bool,
+    pub scrub_text: bool,
+    pub output: Option<String>,
+    pub format: Option<OutputFormat>,
+    pub report: Option<ReportFormat>,
+    pub template: Option<String>,
+    pub behaviors: Option<Vec<String>>,
+    pub filter: FilterConfig,
+    pub behavior: HashMap<String, BehaviorConfig>,
+}
+
+/// The `[filter]` table: one key per filter option, as on the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FilterConfig {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub from_user: Vec<String>,
+    pub exclude_user: Vec<String>,
+    pub chat: Option<String>,
+    pub has_media: bool,
+    pub has_link: bool,
+    pub only_forwarded: bool,
+    pub reply_to: Option<i64>,
+    pub exclude_service: bool,
+    pub exclude_bots: bool,
+    #[serde(rename = "where")]
+    pub expr: Option<String>,
+}
+
+/// A `[behavior.<name>]` table: `format` and `output` override the run's
+/// output for this behavior; every other key is one of its options
+/// (see `list-behaviors`).
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct BehaviorConfig {
+    pub format: Option<OutputFormat>,
+    pub output: Option<String>,
+    #[serde(flatten)]
+    pub options: BTreeMap<String, Value>,
+}
+
+impl Config {
+    /// Loads the config file at `path` or, without one, the first file of the
+    /// default search path that exists (an empty config if there is none),
+    /// with the `[profile.<name>]` section applied when `profile` is given.
+    pub fn load(path: Option<&str>, profile: Option<&str>) -> Result<Config, Box<dyn Error>> {
+        let path = match path {
+            Some(p) => PathBuf::from(p),
+            None => match default_paths().into_iter().find(|p| p.is_file()) {
+                Some(p) => p,
+                None if profile.is_some() => {
+                    return Err(AnalyzerError::Usage(format!(
+                        "--profile needs a config file: pass --config or create {}",
+                        LOCAL_CONFIG
+                    ))
+                    .into())
+                }
+                None => return Ok(Config::default()),
+            },
+        };
+        if !path.is_file() {
+            return Err(AnalyzerError::MissingInput {
+                what: "Config file",
+                path: path.display().to_string(),
+            }
+            .into());
+        }
+        let text = fs::read_to_string(&path).map_err(AnalyzerError::io(&path))?;
+        let invalid = |e: &dyn ToString| {
+            AnalyzerError::Usage(format!("Invalid config file {}: {}", path.display(), e.to_string().trim_end()))
+        };
+        let mut table = text.parse::<Table>().map_err(|e| invalid(&e))?;
+
+        // 1) Lay the chosen profile over the top-level keys
+        let profiles = match table.remove("profile") {
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err(invalid(&"`profile` must be a table").into()),
+            None => Table::new(),
+        };
+        if let Some(name) = profile {
+            match profiles.get(name) {
+                Some(Value::Table(overrides)) => merge(&mut table, overrides.clone()),
+                _ => {
+                    let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                    return Err(AnalyzerError::Usage(format!(
+                        "Unknown profile “{}” in {} (defined: {})",
+                        name,
+                        path.display(),
+                        if known.is_empty() { "none".to_string() } else { known.join(", ") }
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        // 2) Typed settings, paths made relative to the file
+        let mut config = Config::deserialize(Value::Table(table)).map_err(|e| invalid(&e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for p in config
+            .json
+            .iter_mut()
+            .chain(config.folder.as_mut())
+            .chain(config.file.as_mut())
+            .chain(config.output.as_mut())
+            .chain(config.template.as_mut())
+            .chain(config.behavior.values_mut().filter_map(|b| b.output.as_mut()))
+        {
+            *p = base.join(&*p).to_string_lossy().into_owned();
+        }
+        Ok(config)
+    }
+
+    /// The config's `timezone`, if set.
+    pub fn timezone(&self) -> Result<Option<Tz>, Box<dyn Error>> {
+        Ok(self.timezone.as_deref().map(parse_timezone).transpose().map_err(AnalyzerError::Usage)?)
+    }
+}
+
+impl BehaviorConfig {
+    /// The option values as strings, the way they would be given on the command line.
+    pub fn option_values(&self) -> BTreeMap<String, String> {
+        self.options
+            .iter()
+            .map(|(name, value)| match value {
+                Value::String(s) => (name.clone(), s.clone()),
+                other => (name.clone(), other.to_string()),
+            })
+            .collect()
+    }
+}
+
+impl FilterConfig {
+    /// Fills in every option `filter` (from the command line) leaves unset.
+    pub fn apply(&self, filter: &mut MessageFilter) -> Result<(), Box<dyn Error>> {
+        if filter.since.is_none() {
+            filter.since = self.since.as_deref().map(parse_since).transpose().map_err(AnalyzerError::Usage)?;
+        }
+        if filter.until.is_none() {
+            filter.until = self.until.as_deref().map(parse_until).transpose().map_err(AnalyzerError::Usage)?;
+        }
+        if filter.from_users.is_empty() {
+            filter.from_users = self.from_user.clone();
+        }
+        if filter.exclude_users.is_empty() {
+            filter.exclude_users = self.exclude_user.clone();
+        }
+        if filter.chat.is_none() {
+            filter.chat = self.chat.clone();
+        }
+        filter.has_media |= self.has_media;
+        filter.has_link |= self.has_link;
+        filter.only_forwarded |= self.only_forwarded;
+        if filter.reply_to.is_none() {
+            filter.reply_to = self.reply_to;
+        }
+        filter.exclude_service |= self.exclude_service;
+        filter.exclude_bots |= self.exclude_bots;
+        if filter.expr.is_none() {
+            filter.expr = self.expr.as_deref().map(parse_where).transpose().map_err(AnalyzerError::Usage)?;
+        }
+        Ok(())
+    }
+}
+
+/// `--timezone` and `timezone`: an IANA name such as “Europe/Berlin”, or “UTC”.
+pub fn parse_timezone(s: &str) -> Result<Tz, String> {
+    s.parse()
+        .map_err(|_| format!("Unknown timezone “{}”: expected an IANA name like Europe/Berlin, or UTC", s))
+}
+
+/// Where a config file is looked for, in order.
+fn default_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(LOCAL_CONFIG)];
+    if let Some(dir) = config_dir() {
+        paths.push(dir.join("config.toml"));
+    }
+    paths
+}
+
+/// `$XDG_CONFIG_HOME/telegram-analyzer` (`~/.config/...` without it).
+pub fn config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    config_home.map(|dir| dir.join("telegram-analyzer"))
+}
+
+/// Overrides `base` with `overrides`, merging nested tables key by key.
+fn merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(inner)), Value::Table(value)) => merge(inner, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
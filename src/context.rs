@@ -0,0 +1,108 @@
+// src/context.rs
+
+use crate::filter::MessageFilter;
+use crate::loader::load;
+use crate::model::Message;
+use crate::progress;
+use crate::pseudonym::Pseudonymizer;
+use crate::quality::{DataQuality, ParseMode};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Shared state of one CLI run, handed to every behavior.
+///
+///   - Holds the global `MessageFilter` built from the CLI options (and config file).
+///   - Holds the timezone that time-of-day and per-day behaviors bucket in,
+///     and that the filter's plain dates are days of.
+///   - Holds the parse mode: lenient runs (the default) keep a malformed
+///     message and tally it in `data_quality`, strict ones fail on it.
+///   - Loads each set of exports at most once and applies the filter right
+///     there, so all behaviors of an `all`/`group` run analyze the same slice.
+///   - With a `Pseudonymizer`, replaces identities once the filter has run
+///     (so filters still name real senders), before any behavior sees them.
+pub struct Context {
+    pub filter: MessageFilter,
+    pub timezone: Tz,
+    pub mode: ParseMode,
+    pseudonymizer: Option<Pseudonymizer>,
+    cache: Mutex<HashMap<Vec<String>, Arc<Vec<Message>>>>,
+    quality: Mutex<BTreeMap<Vec<String>, DataQuality>>,
+}
+
+impl Context {
+    pub fn new(mut filter: MessageFilter, timezone: Tz, mode: ParseMode) -> Self {
+        filter.in_timezone(timezone);
+        Context {
+            filter,
+            timezone,
+            mode,
+            pseudonymizer: None,
+            cache: Mutex::new(HashMap::new()),
+            quality: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Pseudonymizes every message this context hands out (see `Pseudonymizer`).
+    pub fn with_pseudonymizer(mut self, pseudonymizer: Pseudonymizer) -> Self {
+        self.pseudonymizer = Some(pseudonymizer);
+        self
+    }
+
+    pub fn pseudonymizer(&self) -> Option<&Pseudonymizer> {
+        self.pseudonymizer.as_ref()
+    }
+
+    /// Pseudonymizes `messages` in place when the run does; for messages that
+    /// don't come from `messages` (e.g. search hits).
+    pub fn pseudonymize(&self, messages: &mut [Message]) {
+        if let Some(p) = &self.pseudonymizer {
+            p.apply(messages);
+        }
+    }
+
+    /// The filtered (and pseudonymized) messages of the export(s) (or index) at `paths`, loaded on first use.
+    pub fn messages(&self, paths: &[String]) -> Result<Arc<Vec<Message>>, Box<dyn Error>> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(messages) = cache.get(paths) {
+            return Ok(Arc::clone(messages));
+        }
+
+        let mut messages = self.load(paths)?;
+        if !self.filter.is_empty() {
+            messages.retain(|m| self.filter.matches(m));
+        }
+        self.pseudonymize(&mut messages);
+        let messages = Arc::new(messages);
+        cache.insert(paths.to_vec(), Arc::clone(&messages));
+        Ok(messages)
+    }
+
+    /// Every message of the export, ignoring the filter and not pseudonymized.
+    /// Not cached: for behaviors that need surrounding messages the filter
+    /// would drop (which `pseudonymize` what they return).
+    pub fn unfiltered_messages(&self, paths: &[String]) -> Result<Vec<Message>, Box<dyn Error>> {
+        self.load(paths)
+    }
+
+    /// The malformed messages the loads so far kept, over all inputs (`None` before the first load).
+    pub fn data_quality(&self) -> Option<DataQuality> {
+        let loads = self.quality.lock().unwrap();
+        if loads.is_empty() {
+            return None;
+        }
+        let mut total = DataQuality::default();
+        for quality in loads.values() {
+            total.merge(quality);
+        }
+        Some(total)
+    }
+
+    /// Loads `paths` in the run's mode and records the malformed messages (once per set of paths).
+    fn load(&self, paths: &[String]) -> Result<Vec<Message>, Box<dyn Error>> {
+        let loaded = progress::phase(&format!("load {}", paths.join(", ")), || load(paths, self.mode))?;
+        self.quality.lock().unwrap().insert(paths.to_vec(), loaded.quality);
+        Ok(loaded.messages)
+    }
+}
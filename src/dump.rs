@@ -0,0 +1,69 @@
+// src/dump.rs
+
+use crate::model::Message;
+use crate::report::{Cell, Column, ColumnType, Report};
+use std::collections::HashSet;
+
+/// Version of the `dump` message table, also what plugins read (see `plugin`).
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The normalized message table written by `dump`: one row per message with
+/// its chat, sender, dates, text and attachments. Entities are not included
+/// beyond the links they carry (`links`, newline-separated).
+pub fn message_report(messages: &[Message]) -> Report {
+    let rows: Vec<Vec<Cell>> = messages
+        .iter()
+        .map(|m| {
+            vec![
+                m.chat.id.into(),
+                m.chat.name.as_deref().into(),
+                m.chat.kind.as_deref().into(),
+                m.id.into(),
+                m.kind.as_str().into(),
+                m.date.into(),
+                m.edited.into(),
+                m.from.as_deref().into(),
+                m.from_id.as_deref().into(),
+                m.text.as_str().into(),
+                Some(m.links().join("\n")).filter(|l| !l.is_empty()).into(),
+                m.forwarded_from.as_deref().into(),
+                m.reply_to_message_id.into(),
+                m.via_bot.as_deref().into(),
+                m.media_type.as_deref().into(),
+                m.file.as_deref().into(),
+                m.photo.as_deref().into(),
+                m.source.as_deref().into(),
+            ]
+        })
+        .collect();
+
+    let chats: HashSet<_> = messages.iter().map(|m| (m.chat.id, m.chat.name.as_deref())).collect();
+    Report {
+        behavior: "dump",
+        file_stem: "messages",
+        schema_version: SCHEMA_VERSION,
+        columns: vec![
+            Column::new("chat_id", ColumnType::Integer),
+            Column::new("chat", ColumnType::Category),
+            Column::new("chat_type", ColumnType::Category),
+            Column::new("id", ColumnType::Integer),
+            Column::new("type", ColumnType::Category),
+            Column::new("date", ColumnType::Timestamp),
+            Column::new("edited", ColumnType::Timestamp),
+            Column::new("from", ColumnType::Category),
+            Column::new("from_id", ColumnType::Category),
+            Column::new("text", ColumnType::Text),
+            Column::new("links", ColumnType::Text),
+            Column::new("forwarded_from", ColumnType::Category),
+            Column::new("reply_to_message_id", ColumnType::Integer),
+            Column::new("via_bot", ColumnType::Category),
+            Column::new("media_type", ColumnType::Category),
+            Column::new("file", ColumnType::Text),
+            Column::new("photo", ColumnType::Text),
+            Column::new("source", ColumnType::Category),
+        ],
+        summary: vec![("messages", rows.len().into()), ("chats", chats.len().into())],
+        summary_line: format!("→ Dump: {} messages from {} chats", rows.len(), chats.len()),
+        rows,
+    }
+}
@@ -0,0 +1,198 @@
+// src/error.rs
+
+use crate::quality::Problem;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Process exit codes of the CLI, so a scheduler can tell a bad input from a crash.
+pub mod exit_code {
+    /// Anything unexpected: a bug, or a failure without a more specific code.
+    pub const FAILURE: u8 = 1;
+    /// Invalid command line or config file (clap uses 2 as well).
+    pub const USAGE: u8 = 2;
+    /// An input that is missing, malformed or not a Telegram export.
+    pub const BAD_INPUT: u8 = 3;
+    /// An input that exists but can't be read.
+    pub const IO: u8 = 4;
+    /// A result that can't be written.
+    pub const OUTPUT: u8 = 5;
+}
+
+/// The failures the analyzer tells apart. Fallible functions return
+/// `Box<dyn Error>`; where one of these is the cause, the box holds an
+/// `AnalyzerError`, so callers can `downcast_ref` it for `hint` and `exit_code`.
+#[derive(Debug)]
+pub enum AnalyzerError {
+    /// A given input path doesn't exist; `what` is e.g. “Input file”, “Folder” or “Index”.
+    MissingInput { what: &'static str, path: String },
+    /// A required input wasn't given at all (`flag` is the option supplying it).
+    NoInput { flag: &'static str },
+    /// Not valid JSON (often a truncated download).
+    MalformedJson {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// Valid JSON, but not a shape this tool reads.
+    UnsupportedSchema { path: String, reason: String },
+    /// A message whose `date` is present but not a timestamp (strict mode).
+    BadTimestamp {
+        path: String,
+        message_id: i64,
+        value: String,
+    },
+    /// Any other malformed message (strict mode).
+    MalformedMessage {
+        path: String,
+        message_id: i64,
+        reason: Problem,
+    },
+    /// Reading an input failed.
+    Io { path: PathBuf, source: io::Error },
+    /// Writing a result failed.
+    Write { path: PathBuf, source: io::Error },
+    /// A script (`script --file`) that doesn't compile or fails while running.
+    Script { path: String, message: String },
+    /// A plugin that can't be run or answers with something other than its protocol.
+    Plugin { path: PathBuf, message: String },
+    /// An invalid option value or config setting.
+    Usage(String),
+}
+
+impl AnalyzerError {
+    /// A JSON parse error of the file at `path`: I/O errors stay `Io`, anything
+    /// else is `MalformedJson` with serde's position.
+    pub fn json(path: impl AsRef<Path>, e: serde_json::Error) -> Self {
+        let path = path.as_ref();
+        if e.is_io() {
+            return AnalyzerError::Io {
+                path: path.to_path_buf(),
+                source: e.into(),
+            };
+        }
+        // serde appends " at line L column C" to the message; the position is kept apart
+        let message = e.to_string();
+        let message = message.split(" at line ").next().unwrap_or(&message).to_string();
+        AnalyzerError::MalformedJson {
+            path: path.display().to_string(),
+            line: e.line(),
+            column: e.column(),
+            message,
+        }
+    }
+
+    /// Wraps a read error of `path`.
+    pub fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |source| AnalyzerError::Io { path, source }
+    }
+
+    /// Wraps a write error of `path`.
+    pub fn write(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |source| AnalyzerError::Write { path, source }
+    }
+
+    /// What to do about it, printed under the error.
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            AnalyzerError::MissingInput { .. } => {
+                "Check the path; paths set in the config file are relative to its folder".to_string()
+            }
+            AnalyzerError::NoInput { flag } => format!("Pass {} (or set it in the config file)", flag),
+            AnalyzerError::MalformedJson { .. } => {
+                "The file isn't valid JSON, often because the export was cut off; export the chat again \
+                 (Telegram Desktop: Export chat history, format “Machine-readable JSON”)"
+                    .to_string()
+            }
+            AnalyzerError::UnsupportedSchema { .. } => {
+                "Expected the result.json of a Telegram Desktop export in JSON format, not the HTML export"
+                    .to_string()
+            }
+            AnalyzerError::BadTimestamp { .. } => {
+                "Dates look like 2024-01-31T12:00:00; fix the message's `date` in the export, \
+                 or drop --strict to keep such messages out of time-based behaviors"
+                    .to_string()
+            }
+            AnalyzerError::MalformedMessage { .. } => {
+                "Drop --strict to keep malformed messages and get a data-quality report of them".to_string()
+            }
+            AnalyzerError::Io { .. } => "Check that the file is readable by the current user".to_string(),
+            AnalyzerError::Write { .. } => {
+                "Check that the output folder is writable and the disk isn't full".to_string()
+            }
+            AnalyzerError::Script { .. } => {
+                "Positions are line and column in the script; `script --help` lists what scripts can call".to_string()
+            }
+            AnalyzerError::Plugin { path, .. } => format!(
+                "Plugins are external programs; try it by hand, e.g. `{} describe`, \
+                 or remove it from the plugin folder",
+                path.display()
+            ),
+            AnalyzerError::Usage(_) => return None,
+        };
+        Some(hint)
+    }
+
+    /// The process exit code for this error (see `exit_code`).
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            AnalyzerError::MissingInput { .. }
+            | AnalyzerError::NoInput { .. }
+            | AnalyzerError::MalformedJson { .. }
+            | AnalyzerError::UnsupportedSchema { .. }
+            | AnalyzerError::BadTimestamp { .. }
+            | AnalyzerError::MalformedMessage { .. } => exit_code::BAD_INPUT,
+            AnalyzerError::Io { .. } => exit_code::IO,
+            AnalyzerError::Write { .. } => exit_code::OUTPUT,
+            AnalyzerError::Script { .. } => exit_code::BAD_INPUT,
+            AnalyzerError::Plugin { .. } => exit_code::FAILURE,
+            AnalyzerError::Usage(_) => exit_code::USAGE,
+        }
+    }
+}
+
+impl fmt::Display for AnalyzerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalyzerError::MissingInput { what, path } => write!(f, "{} not found: {}", what, path),
+            AnalyzerError::NoInput { flag } => write!(f, "No input given: {} is required", flag),
+            AnalyzerError::MalformedJson {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "Malformed JSON in {} at line {}, column {}: {}", path, line, column, message),
+            AnalyzerError::UnsupportedSchema { path, reason } => {
+                write!(f, "Unsupported export format in {}: {}", path, reason)
+            }
+            AnalyzerError::BadTimestamp {
+                path,
+                message_id,
+                value,
+            } => write!(f, "Bad timestamp “{}” on message {} in {}", value, message_id, path),
+            AnalyzerError::MalformedMessage {
+                path,
+                message_id,
+                reason,
+            } => write!(f, "Message {} in {} {}", message_id, path, reason.description()),
+            AnalyzerError::Io { path, source } => write!(f, "Cannot read {}: {}", path.display(), source),
+            AnalyzerError::Write { path, source } => write!(f, "Cannot write {}: {}", path.display(), source),
+            AnalyzerError::Script { path, message } => write!(f, "Script {} failed: {}", path, message),
+            AnalyzerError::Plugin { path, message } => write!(f, "Plugin {} failed: {}", path.display(), message),
+            AnalyzerError::Usage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for AnalyzerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnalyzerError::Io { source, .. } | AnalyzerError::Write { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+// src/filter.rs
+
+use crate::model::Message;
+use crate::query::Expr;
+use chrono::{DateTime, Days, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use clap::Args;
+
+/// Message selection applied once, when an export is loaded, so every
+/// behavior of a run analyzes the same slice.
+///
+/// Every set option must match for a message to be kept; unset options
+/// don't restrict anything. All options are global: they can be given
+/// before or after the subcommand. Plain dates are days of the run's
+/// timezone once [`MessageFilter::in_timezone`] has placed them (UTC until then).
+#[derive(Debug, Clone, Default, Args)]
+pub struct MessageFilter {
+    /// (Optional) Only messages sent at or after this date (YYYY-MM-DD, a day of --timezone, or RFC 3339).
+    #[arg(long, global = true, value_parser = parse_since)]
+    pub since: Option<DateBound>,
+
+    /// (Optional) Only messages sent before this date; a plain YYYY-MM-DD includes that whole day.
+    #[arg(long, global = true, value_parser = parse_until)]
+    pub until: Option<DateBound>,
+
+    /// (Optional) Only messages from this sender (matches `from_id` like “user123” or the display name). Repeatable.
+    #[arg(long = "from-user", global = true)]
+    pub from_users: Vec<String>,
+
+    /// (Optional) Drop messages from this sender (`from_id` or display name). Repeatable.
+    #[arg(long = "exclude-user", global = true)]
+    pub exclude_users: Vec<String>,
+
+    /// (Optional) Only messages from this chat (chat id or chat name).
+    #[arg(long, global = true)]
+    pub chat: Option<String>,
+
+    /// Only messages with a photo, file or other media attachment.
+    #[arg(long, global = true)]
+    pub has_media: bool,
+
+    /// Only messages containing at least one link.
+    #[arg(long, global = true)]
+    pub has_link: bool,
+
+    /// Only forwarded messages.
+    #[arg(long, global = true)]
+    pub only_forwarded: bool,
+
+    /// (Optional) Only replies to the message with this id.
+    #[arg(long, global = true)]
+    pub reply_to: Option<i64>,
+
+    /// Drop service messages (joins, pins, title changes, …).
+    #[arg(long, global = true)]
+    pub exclude_service: bool,
+
+    /// Drop messages sent by bots (the bot of a bot chat; not users posting via an inline bot).
+    #[arg(long, global = true)]
+    pub exclude_bots: bool,
+
+    /// (Optional) Filter expression, e.g. 'from:user123 AND date>=2024-01-01 AND (has:link OR text~"crypto") AND NOT forwarded'.
+    /// Fields: from, chat, date, id, reply, type, media, has, text, forwarded_from;
+    /// flags: forwarded, service, bot, via_bot, edited, reply.
+    #[arg(long = "where", global = true, value_name = "EXPR", value_parser = parse_where)]
+    pub expr: Option<Expr>,
+}
+
+/// A `--since`/`--until` bound: an instant, or the midnight starting a day,
+/// whose instant depends on the timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBound {
+    Instant(DateTime<Utc>),
+    Midnight(NaiveDate),
+}
+
+impl DateBound {
+    /// The bound as an instant; a midnight not yet placed in a timezone is UTC's.
+    pub fn instant(&self) -> DateTime<Utc> {
+        match self {
+            DateBound::Instant(instant) => *instant,
+            DateBound::Midnight(day) => day.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        }
+    }
+}
+
+/// The first instant of `day` in `timezone`: its midnight, or the end of a
+/// DST gap that swallows midnight (as in America/Santiago).
+pub fn start_of_day(day: NaiveDate, timezone: Tz) -> DateTime<Utc> {
+    (0..24)
+        .find_map(|hour| timezone.from_local_datetime(&day.and_hms_opt(hour, 0, 0)?).earliest())
+        .map_or_else(|| DateBound::Midnight(day).instant(), |start| start.with_timezone(&Utc))
+}
+
+impl MessageFilter {
+    /// Places plain dates (of `--since`, `--until` and `--where`) in
+    /// `timezone`, so they mean the same days as the behaviors' daily buckets.
+    pub fn in_timezone(&mut self, timezone: Tz) {
+        for bound in [&mut self.since, &mut self.until].into_iter().flatten() {
+            if let DateBound::Midnight(day) = *bound {
+                *bound = DateBound::Instant(start_of_day(day, timezone));
+            }
+        }
+        if let Some(expr) = &mut self.expr {
+            expr.in_timezone(timezone);
+        }
+    }
+
+    /// True if no option is set, i.e. every message passes.
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none()
+            && self.until.is_none()
+            && self.from_users.is_empty()
+            && self.exclude_users.is_empty()
+            && self.chat.is_none()
+            && !self.has_media
+            && !self.has_link
+            && !self.only_forwarded
+            && self.reply_to.is_none()
+            && !self.exclude_service
+            && !self.exclude_bots
+            && self.expr.is_none()
+    }
+
+    /// Returns true if `msg` passes every option that is set.
+    pub fn matches(&self, msg: &Message) -> bool {
+        if self.since.is_some() || self.until.is_some() {
+            let Some(date) = msg.date else {
+                return false;
+            };
+            if self.since.is_some_and(|since| date < since.instant()) {
+                return false;
+            }
+            if self.until.is_some_and(|until| date >= until.instant()) {
+                return false;
+            }
+        }
+        if !self.from_users.is_empty() && !self.from_users.iter().any(|u| is_sender(msg, u)) {
+            return false;
+        }
+        if self.exclude_users.iter().any(|u| is_sender(msg, u)) {
+            return false;
+        }
+        if let Some(chat) = &self.chat {
+            let id_matches = msg.chat.id.is_some_and(|id| id.to_string() == *chat);
+            let name_matches = msg.chat.name.as_deref() == Some(chat.as_str());
+            if !id_matches && !name_matches {
+                return false;
+            }
+        }
+        if self.has_media && !msg.has_media() {
+            return false;
+        }
+        if self.has_link && !msg.has_link() {
+            return false;
+        }
+        if self.only_forwarded && !msg.is_forwarded() {
+            return false;
+        }
+        if self.reply_to.is_some() && msg.reply_to_message_id != self.reply_to {
+            return false;
+        }
+        if self.exclude_service && msg.is_service() {
+            return false;
+        }
+        if self.exclude_bots && msg.is_bot() {
+            return false;
+        }
+        if self.expr.as_ref().is_some_and(|e| !e.matches(msg)) {
+            return false;
+        }
+        true
+    }
+}
+
+/// True if `user` names the sender of `msg`, either by `from_id` or by display name.
+pub fn is_sender(msg: &Message, user: &str) -> bool {
+    msg.from_id.as_deref() == Some(user) || msg.from.as_deref() == Some(user)
+}
+
+/// `--where`: parse errors point at the offending part of the expression.
+pub fn parse_where(s: &str) -> Result<Expr, String> {
+    Expr::parse(s).map_err(|e| e.to_string())
+}
+
+/// `--since`: a plain date means the midnight starting that day.
+pub fn parse_since(s: &str) -> Result<DateBound, String> {
+    if let Ok(day) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(DateBound::Midnight(day));
+    }
+    parse_rfc3339(s)
+}
+
+/// `--until`: a plain date means the end of that day (exclusive bound at the next midnight).
+pub fn parse_until(s: &str) -> Result<DateBound, String> {
+    if let Ok(day) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let next = day
+            .checked_add_days(Days::new(1))
+            .ok_or_else(|| format!("Date out of range: {}", s))?;
+        return Ok(DateBound::Midnight(next));
+    }
+    parse_rfc3339(s)
+}
+
+fn parse_rfc3339(s: &str) -> Result<DateBound, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| DateBound::Instant(dt.with_timezone(&Utc)))
+        .map_err(|_| format!("Invalid date “{}”: expected YYYY-MM-DD or RFC 3339", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Chat;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn sent_at(date: &str) -> Message {
+        let value = json!({ "id": 1, "type": "message", "date": date, "text": "" });
+        Message::from_json(&value, Arc::new(Chat::default())).unwrap()
+    }
+
+    fn filter(since: &str, until: &str, timezone: Tz) -> MessageFilter {
+        let mut filter = MessageFilter {
+            since: Some(parse_since(since).unwrap()),
+            until: Some(parse_until(until).unwrap()),
+            ..MessageFilter::default()
+        };
+        filter.in_timezone(timezone);
+        filter
+    }
+
+    #[test]
+    fn plain_dates_are_days_of_the_timezone() {
+        let june = filter("2024-06-01", "2024-06-30", chrono_tz::Europe::Berlin);
+        // Berlin is two hours ahead of UTC in summer
+        assert!(!june.matches(&sent_at("2024-05-31T21:59:59")));
+        assert!(june.matches(&sent_at("2024-05-31T22:00:00")));
+        assert!(june.matches(&sent_at("2024-06-30T21:59:59")));
+        assert!(!june.matches(&sent_at("2024-06-30T22:00:00")));
+
+        let utc = filter("2024-06-01", "2024-06-30", chrono_tz::UTC);
+        assert!(!utc.matches(&sent_at("2024-05-31T22:00:00")));
+        assert!(utc.matches(&sent_at("2024-06-30T22:00:00")));
+    }
+
+    #[test]
+    fn timestamps_ignore_the_timezone() {
+        let window = filter("2024-06-01T12:00:00Z", "2024-06-01T14:00:00+01:00", chrono_tz::Asia::Tokyo);
+        assert!(!window.matches(&sent_at("2024-06-01T11:59:59")));
+        assert!(window.matches(&sent_at("2024-06-01T12:00:00")));
+        assert!(!window.matches(&sent_at("2024-06-01T13:00:00")));
+    }
+
+    #[test]
+    fn a_day_starts_after_a_dst_gap_at_midnight() {
+        // Santiago skipped from 00:00 to 01:00 (-04:00 to -03:00) on 2023-09-03
+        let start = start_of_day(NaiveDate::from_ymd_opt(2023, 9, 3).unwrap(), chrono_tz::America::Santiago);
+        assert_eq!(start.to_rfc3339(), "2023-09-03T04:00:00+00:00");
+    }
+}
@@ -0,0 +1,211 @@
+// src/html.rs
+
+use crate::charts::Chart;
+use crate::report::{Cell, ColumnType, Report};
+use chrono::{SecondsFormat, Utc};
+use std::fmt::Write;
+
+/// How many rows the "top" tables show.
+const TOP_ROWS: usize = 20;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Roboto, Helvetica, Arial, sans-serif; margin: 0; color: #1f2933; background: #f5f7fa; }
+header { background: #243b53; color: #fff; padding: 24px 40px; }
+header h1 { margin: 0 0 4px; font-size: 24px; }
+header p { margin: 0; opacity: .8; font-size: 13px; }
+main { max-width: 1100px; margin: 0 auto; padding: 24px 40px 48px; }
+section { background: #fff; border-radius: 6px; box-shadow: 0 1px 3px rgba(0,0,0,.1); padding: 20px 24px; margin-bottom: 24px; }
+h2 { margin-top: 0; font-size: 18px; color: #243b53; }
+.cards { display: flex; flex-wrap: wrap; gap: 12px; }
+.card { flex: 1 1 150px; background: #f0f4f8; border-radius: 6px; padding: 12px 16px; }
+.card .value { font-size: 22px; font-weight: 600; }
+.card .label { font-size: 12px; color: #627d98; text-transform: uppercase; letter-spacing: .04em; }
+figure { margin: 0 0 16px; }
+figure svg { max-width: 100%; height: auto; }
+table { border-collapse: collapse; width: 100%; font-size: 13px; }
+th, td { text-align: left; padding: 6px 8px; border-bottom: 1px solid #e4e7eb; vertical-align: top; }
+th { background: #f0f4f8; cursor: pointer; user-select: none; white-space: nowrap; }
+td.num, th.num { text-align: right; }
+td { word-break: break-word; }
+details { margin-bottom: 12px; }
+summary { cursor: pointer; font-weight: 600; }
+.empty { color: #829ab1; font-style: italic; }
+"#;
+
+/// Sorts a table by the clicked header; numeric columns sort numerically.
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table"), body = table.tBodies[0];
+    var index = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var numeric = th.classList.contains("num");
+    var ascending = th.dataset.order !== "asc";
+    th.parentNode.querySelectorAll("th").forEach(function (h) { delete h.dataset.order; });
+    th.dataset.order = ascending ? "asc" : "desc";
+    var rows = Array.prototype.slice.call(body.rows);
+    rows.sort(function (a, b) {
+      var x = a.cells[index].textContent, y = b.cells[index].textContent;
+      var c = numeric ? (parseFloat(x) || 0) - (parseFloat(y) || 0) : x.localeCompare(y);
+      return ascending ? c : -c;
+    });
+    rows.forEach(function (r) { body.appendChild(r); });
+  });
+});
+"#;
+
+/// Renders the reports of an `all` run as one self-contained HTML page
+/// (inline CSS and JS, no external resources).
+///
+/// Sections, each shown only when the behavior behind it ran:
+///   - Overview: headline numbers from message-stats, count-daily, count-urls,
+///     shares and list-extensions.
+///   - Activity: the `charts` (inline SVG), when given.
+///   - Top URLs (count-urls) and top users (user-interactions).
+///   - Text statistics (text-stats) and file types (list-extensions).
+///   - All results: every report's full table, collapsed.
+pub fn render(reports: &[Report], charts: &[Chart], title: &str, inputs: &[String]) -> String {
+    let find = |behavior: &str| reports.iter().find(|r| r.behavior == behavior);
+    let mut body = String::new();
+
+    // 1) Overview
+    let mut cards: Vec<(String, &str)> = Vec::new();
+    if let Some(stats) = find("message-stats") {
+        push_card(&mut cards, stats.summary_value("messages"), "messages");
+        push_card(&mut cards, stats.summary_value("distinct_users"), "users");
+    }
+    if let Some(daily) = find("count-daily") {
+        push_card(&mut cards, daily.summary_value("days"), "active days");
+        if let (Some(first), Some(last)) = (daily.rows.first(), daily.rows.last()) {
+            cards.push((format!("{} – {}", first[0].to_text(), last[0].to_text()), "period"));
+        }
+    }
+    if let Some(urls) = find("count-urls") {
+        push_card(&mut cards, urls.summary_value("unique_urls"), "unique URLs");
+    }
+    if let Some(shares) = find("shares") {
+        push_card(&mut cards, shares.summary_value("media"), "media shared");
+    }
+    if let Some(extensions) = find("list-extensions") {
+        push_card(&mut cards, extensions.summary_value("files"), "files scanned");
+    }
+    if !cards.is_empty() {
+        body.push_str("<section id=\"overview\"><h2>Overview</h2><div class=\"cards\">");
+        for (value, label) in &cards {
+            let _ = write!(
+                body,
+                "<div class=\"card\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>",
+                escape(value),
+                escape(label)
+            );
+        }
+        body.push_str("</div></section>\n");
+    }
+
+    // 2) Activity
+    if !charts.is_empty() {
+        body.push_str("<section id=\"activity\"><h2>Activity</h2>");
+        for chart in charts {
+            let _ = write!(body, "<figure id=\"{}\">{}</figure>", chart.name, chart.svg);
+        }
+        body.push_str("</section>\n");
+    }
+
+    // 3) Top tables
+    if let Some(report) = find("count-urls") {
+        section_table(&mut body, "top-urls", "Top URLs", report, TOP_ROWS);
+    }
+    if let Some(report) = find("user-interactions") {
+        section_table(&mut body, "top-users", "Top users", report, TOP_ROWS);
+    }
+    if let Some(report) = find("text-stats") {
+        section_table(&mut body, "text", "Text statistics", report, usize::MAX);
+    }
+    if let Some(report) = find("list-extensions") {
+        section_table(&mut body, "file-types", "File types", report, usize::MAX);
+    }
+
+    // 4) Everything, collapsed
+    body.push_str("<section id=\"all-results\"><h2>All results</h2>");
+    for report in reports {
+        let _ = write!(
+            body,
+            "<details><summary>{} ({} rows)</summary>{}</details>",
+            escape(report.behavior),
+            report.rows.len(),
+            table(report, usize::MAX)
+        );
+    }
+    body.push_str("</section>\n");
+
+    let generated = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <header><h1>{title}</h1><p>Generated {generated} from {inputs}</p></header>\n\
+         <main>\n{body}</main>\n<script>{SCRIPT}</script>\n</body>\n</html>\n",
+        title = escape(title),
+        inputs = escape(&inputs.join(", ")),
+    )
+}
+
+fn push_card<'a>(cards: &mut Vec<(String, &'a str)>, value: Option<&Cell>, label: &'a str) {
+    if let Some(value) = value {
+        cards.push((value.to_text(), label));
+    }
+}
+
+fn section_table(body: &mut String, id: &str, title: &str, report: &Report, limit: usize) {
+    let _ = writeln!(
+        body,
+        "<section id=\"{}\"><h2>{}</h2>{}</section>",
+        id,
+        escape(title),
+        table(report, limit)
+    );
+}
+
+/// A sortable table of the first `limit` rows.
+fn table(report: &Report, limit: usize) -> String {
+    if report.rows.is_empty() {
+        return "<p class=\"empty\">No rows</p>".to_string();
+    }
+    let numeric: Vec<bool> = report
+        .columns
+        .iter()
+        .map(|c| matches!(c.kind, ColumnType::Integer | ColumnType::Float))
+        .collect();
+
+    let mut html = String::from("<table class=\"sortable\"><thead><tr>");
+    for (column, num) in report.columns.iter().zip(&numeric) {
+        let _ = write!(html, "<th{}>{}</th>", if *num { " class=\"num\"" } else { "" }, escape(column.name));
+    }
+    html.push_str("</tr></thead><tbody>");
+    for row in report.rows.iter().take(limit) {
+        html.push_str("<tr>");
+        for (cell, num) in row.iter().zip(&numeric) {
+            let text = cell.to_text();
+            let content = if text.starts_with("http://") || text.starts_with("https://") {
+                format!("<a href=\"{0}\" rel=\"noopener noreferrer\">{0}</a>", escape(&text))
+            } else {
+                escape(&text)
+            };
+            let _ = write!(html, "<td{}>{}</td>", if *num { " class=\"num\"" } else { "" }, content);
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+    if report.rows.len() > limit {
+        let _ = write!(html, "<p class=\"empty\">{} more rows not shown</p>", report.rows.len() - limit);
+    }
+    html
+}
+
+/// `s` escaped for HTML and SVG (XML) text and attribute values.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
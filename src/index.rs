@@ -0,0 +1,590 @@
+// src/index.rs
+
+use crate::error::AnalyzerError;
+use crate::filter::MessageFilter;
+use crate::loader::{normalize, read_chats};
+use crate::model::{tokenize, Chat, Message};
+use crate::quality::{DataQuality, ParseMode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Metadata and document table, rewritten on every update.
+pub const INDEX_FILE: &str = "index.json";
+/// Append-only store: one raw message JSON object per line.
+pub const STORE_FILE: &str = "messages.jsonl";
+/// Each update writes the postings of the docs it added to a file of its own
+/// (`postings-<n>.json`): earlier ones are never rewritten, and only searches read them.
+const POSTINGS_PREFIX: &str = "postings-";
+
+const INDEX_VERSION: u32 = 2;
+
+/// Terms mapped to the docs containing them: trigrams of the lowercased text
+/// tokens, plus metadata terms (`from:<id>`, `has:media`, …).
+type Postings = BTreeMap<String, Vec<u32>>;
+
+/// One indexed export, recorded for provenance.
+#[derive(Debug, Serialize, Deserialize)]
+struct Source {
+    path: String,
+    indexed_at: String,
+    added: usize,
+    updated: usize,
+}
+
+/// One line of the store. Superseded versions stay in the store with `live = false`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Doc {
+    offset: u64,
+    len: u64,
+    chat: u32,
+    id: i64,
+    date: Option<i64>,
+    edited: Option<i64>,
+    live: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexData {
+    version: u32,
+    chats: Vec<Chat>,
+    docs: Vec<Doc>,
+    sources: Vec<Source>,
+    /// The postings files, oldest first.
+    postings: Vec<String>,
+}
+
+/// Counts reported by [`Index::add_export`].
+#[derive(Debug, Default)]
+pub struct UpdateStats {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Malformed messages among them (lenient mode).
+    pub quality: DataQuality,
+}
+
+/// On-disk full-text and metadata index over one or more exports.
+///
+///   - Messages are keyed by `(chat, message id)`; re-adding an export only
+///     appends messages that are new or carry a newer `edited` timestamp.
+///   - Text terms are the trigrams of lowercased alphanumeric tokens; literal
+///     queries are narrowed to docs having every trigram of each query token
+///     (tokens shorter than three characters don't narrow).
+///   - The loader accepts an index directory anywhere an export path is expected.
+pub struct Index {
+    dir: PathBuf,
+    data: IndexData,
+    /// Postings of the docs added since the last [`Index::save`].
+    pending: Postings,
+}
+
+/// True if `path` is a directory holding an index.
+pub fn is_index(path: &str) -> bool {
+    Path::new(path).join(INDEX_FILE).is_file()
+}
+
+impl Index {
+    /// Opens an existing index.
+    pub fn open(dir: &str) -> Result<Self, Box<dyn Error>> {
+        let path = Path::new(dir).join(INDEX_FILE);
+        if !path.is_file() {
+            return Err(AnalyzerError::MissingInput {
+                what: "Index",
+                path: dir.to_string(),
+            }
+            .into());
+        }
+        let file = File::open(&path).map_err(AnalyzerError::io(&path))?;
+        let data: IndexData =
+            serde_json::from_reader(BufReader::new(file)).map_err(|e| AnalyzerError::json(&path, e))?;
+        if data.version != INDEX_VERSION {
+            return Err(AnalyzerError::UnsupportedSchema {
+                path: dir.to_string(),
+                reason: format!(
+                    "index version {} (expected {}); rebuild it with `index`",
+                    data.version, INDEX_VERSION
+                ),
+            }
+            .into());
+        }
+        Ok(Index {
+            dir: PathBuf::from(dir),
+            data,
+            pending: Postings::new(),
+        })
+    }
+
+    /// Opens the index in `dir`, or starts an empty one there.
+    pub fn open_or_create(dir: &str) -> Result<Self, Box<dyn Error>> {
+        if is_index(dir) {
+            return Self::open(dir);
+        }
+        fs::create_dir_all(dir).map_err(AnalyzerError::write(dir))?;
+        Ok(Index {
+            dir: PathBuf::from(dir),
+            data: IndexData {
+                version: INDEX_VERSION,
+                ..IndexData::default()
+            },
+            pending: Postings::new(),
+        })
+    }
+
+    /// Number of messages currently visible (latest version of each).
+    pub fn live_count(&self) -> usize {
+        self.data.docs.iter().filter(|d| d.live).count()
+    }
+
+    /// Adds (or refreshes) every message of the export at `path`. Malformed
+    /// messages fail a `Strict` update; a `Lenient` one keeps them and tallies
+    /// them in [`UpdateStats::quality`].
+    /// Call [`Index::save`] afterwards to persist the result.
+    pub fn add_export(&mut self, path: &str, mode: ParseMode) -> Result<UpdateStats, Box<dyn Error>> {
+        let chats = read_chats(path)?;
+        let source: Arc<str> = Arc::from(path);
+
+        let store_path = self.dir.join(STORE_FILE);
+        let store = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&store_path)
+            .map_err(AnalyzerError::write(&store_path))?;
+        let mut offset = store.metadata().map_err(AnalyzerError::io(&store_path))?.len();
+        let mut writer = BufWriter::new(store);
+
+        let mut live: HashMap<(u32, i64), u32> = self
+            .data
+            .docs
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.live)
+            .map(|(n, d)| ((d.chat, d.id), n as u32))
+            .collect();
+
+        let mut stats = UpdateStats::default();
+        for export_chat in chats {
+            let chat_idx = self.chat_index(export_chat.chat);
+            let chat = Arc::new(self.data.chats[chat_idx as usize].clone());
+
+            for value in export_chat.messages {
+                let Some(msg) = normalize(&value, &chat, &source, mode, &mut stats.quality)? else {
+                    continue;
+                };
+                let edited = msg.edited.map(|d| d.timestamp());
+                match live.get(&(chat_idx, msg.id)) {
+                    Some(&doc) if !is_newer(edited, self.data.docs[doc as usize].edited) => {
+                        stats.unchanged += 1;
+                        continue;
+                    }
+                    Some(&doc) => {
+                        self.data.docs[doc as usize].live = false;
+                        stats.updated += 1;
+                    }
+                    None => stats.added += 1,
+                }
+
+                let line = serde_json::to_string(&value)?;
+                writeln!(writer, "{}", line).map_err(AnalyzerError::write(&store_path))?;
+
+                let doc = self.data.docs.len() as u32;
+                self.data.docs.push(Doc {
+                    offset,
+                    len: line.len() as u64,
+                    chat: chat_idx,
+                    id: msg.id,
+                    date: msg.date.map(|d| d.timestamp()),
+                    edited,
+                    live: true,
+                });
+                offset += line.len() as u64 + 1;
+
+                for term in message_terms(&msg) {
+                    self.pending.entry(term).or_default().push(doc);
+                }
+                live.insert((chat_idx, msg.id), doc);
+            }
+        }
+        writer.flush().map_err(AnalyzerError::write(&store_path))?;
+
+        self.data.sources.push(Source {
+            path: path.to_string(),
+            indexed_at: Utc::now().to_rfc3339(),
+            added: stats.added,
+            updated: stats.updated,
+        });
+        Ok(stats)
+    }
+
+    /// Writes the postings added since the last save to a new postings file,
+    /// then `index.json` listing it; both atomically (temp file + rename).
+    pub fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.pending.is_empty() {
+            let name = format!("{}{}.json", POSTINGS_PREFIX, self.data.postings.len());
+            write_atomically(&self.dir.join(&name), &self.pending)?;
+            self.data.postings.push(name);
+            self.pending.clear();
+        }
+        write_atomically(&self.dir.join(INDEX_FILE), &self.data)
+    }
+
+    /// Live docs grouped by chat, each group ordered by message id.
+    pub fn chat_runs(&self) -> Vec<Vec<u32>> {
+        let mut live: Vec<u32> = (0..self.data.docs.len() as u32)
+            .filter(|&n| self.data.docs[n as usize].live)
+            .collect();
+        live.sort_by_key(|&n| {
+            let d = &self.data.docs[n as usize];
+            (d.chat, d.id)
+        });
+
+        let mut runs: Vec<Vec<u32>> = Vec::new();
+        for n in live {
+            let chat = self.data.docs[n as usize].chat;
+            match runs.last_mut() {
+                Some(run) if self.data.docs[run[0] as usize].chat == chat => run.push(n),
+                _ => runs.push(vec![n]),
+            }
+        }
+        runs
+    }
+
+    /// Reads and normalizes `docs` from the store, in one pass in store order.
+    pub fn read_many(&self, docs: &[u32]) -> Result<HashMap<u32, Message>, Box<dyn Error>> {
+        let mut docs = docs.to_vec();
+        docs.sort_by_key(|&n| self.data.docs[n as usize].offset);
+        docs.dedup();
+        let mut parsed = HashMap::with_capacity(docs.len());
+        if docs.is_empty() {
+            return Ok(parsed);
+        }
+
+        let store = self.dir.join(STORE_FILE);
+        let mut reader = BufReader::new(File::open(&store).map_err(AnalyzerError::io(&store))?);
+        let mut position = 0;
+        for n in docs {
+            let d = &self.data.docs[n as usize];
+            if d.offset != position {
+                // Relative, so a short skip stays within the buffer
+                reader
+                    .seek_relative(d.offset as i64 - position as i64)
+                    .map_err(AnalyzerError::io(&store))?;
+            }
+            let mut buf = vec![0; d.len as usize];
+            reader.read_exact(&mut buf).map_err(AnalyzerError::io(&store))?;
+            position = d.offset + d.len;
+            parsed.insert(n, self.parse_doc(d, &buf)?);
+        }
+        Ok(parsed)
+    }
+
+    /// Loads every live message, chat by chat and ordered by id within a chat.
+    pub fn load_all(&self) -> Result<Vec<Message>, Box<dyn Error>> {
+        // 1) Read the live docs in one pass over the store
+        let live: Vec<u32> = (0..self.data.docs.len() as u32)
+            .filter(|&n| self.data.docs[n as usize].live)
+            .collect();
+        let mut parsed = self.read_many(&live)?;
+
+        // 2) Emit them in chat-run order, sharing one `Chat` per run
+        let mut messages = Vec::with_capacity(parsed.len());
+        for run in self.chat_runs() {
+            let chat = Arc::new(self.data.chats[self.data.docs[run[0] as usize].chat as usize].clone());
+            for n in run {
+                if let Some(mut m) = parsed.remove(&n) {
+                    m.chat = Arc::clone(&chat);
+                    messages.push(m);
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Docs that may match `filter` and (if given) the literal `query`,
+    /// or `None` when nothing narrows the search. Callers still have to
+    /// check each candidate: this is a superset of the real matches.
+    pub fn candidates(
+        &self,
+        filter: &MessageFilter,
+        query: Option<&str>,
+    ) -> Result<Option<HashSet<u32>>, Box<dyn Error>> {
+        // 1) Terms a candidate must have, as groups of alternatives
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        for token in query.into_iter().flat_map(tokenize) {
+            groups.extend(trigrams(&token).into_iter().map(|t| vec![t]));
+        }
+        if !filter.from_users.is_empty() {
+            groups.push(filter.from_users.iter().map(|u| format!("from:{}", u)).collect());
+        }
+        for (wanted, term) in [
+            (filter.has_media, "has:media"),
+            (filter.has_link, "has:link"),
+            (filter.only_forwarded, "is:forwarded"),
+        ] {
+            if wanted {
+                groups.push(vec![term.to_string()]);
+            }
+        }
+        if let Some(reply_to) = filter.reply_to {
+            groups.push(vec![format!("reply:{}", reply_to)]);
+        }
+
+        // 2) Their docs, from the postings files (only read when a term narrows)
+        let mut result = None;
+        if !groups.is_empty() {
+            let postings = self.read_postings()?;
+            result = groups
+                .iter()
+                .map(|group| {
+                    let docs = |term| postings.iter().filter_map(move |p| p.get(term)).flatten().copied();
+                    group.iter().flat_map(docs).collect::<HashSet<u32>>()
+                })
+                .reduce(|a, b| a.intersection(&b).copied().collect());
+        }
+
+        // Date and chat live in the doc table rather than the postings
+        if filter.since.is_some() || filter.until.is_some() || filter.chat.is_some() {
+            let since = filter.since.map(|d| d.instant().timestamp());
+            let until = filter.until.map(|d| d.instant().timestamp());
+            let keep = |n: u32| {
+                let d = &self.data.docs[n as usize];
+                let chat = &self.data.chats[d.chat as usize];
+                let in_range = match d.date {
+                    Some(date) => since.is_none_or(|s| date >= s) && until.is_none_or(|u| date < u),
+                    None => since.is_none() && until.is_none(),
+                };
+                let in_chat = filter.chat.as_ref().is_none_or(|c| {
+                    chat.id.is_some_and(|id| id.to_string() == *c) || chat.name.as_deref() == Some(c.as_str())
+                });
+                in_range && in_chat
+            };
+            result = Some(match result {
+                Some(set) => set.into_iter().filter(|&n| keep(n)).collect(),
+                None => (0..self.data.docs.len() as u32).filter(|&n| keep(n)).collect(),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Every saved postings file, oldest first.
+    fn read_postings(&self) -> Result<Vec<Postings>, Box<dyn Error>> {
+        self.data
+            .postings
+            .iter()
+            .map(|name| {
+                let path = self.dir.join(name);
+                let file = File::open(&path).map_err(AnalyzerError::io(&path))?;
+                Ok(serde_json::from_reader(BufReader::new(file)).map_err(|e| AnalyzerError::json(&path, e))?)
+            })
+            .collect()
+    }
+
+    fn parse_doc(&self, d: &Doc, bytes: &[u8]) -> Result<Message, Box<dyn Error>> {
+        let corrupt = || AnalyzerError::UnsupportedSchema {
+            path: self.dir.display().to_string(),
+            reason: format!("corrupt entry at offset {}; rebuild the index with `index`", d.offset),
+        };
+        let value: Value = serde_json::from_slice(bytes).map_err(|_| corrupt())?;
+        let chat = Arc::new(self.data.chats[d.chat as usize].clone());
+        Message::from_json(&value, chat).ok_or_else(|| corrupt().into())
+    }
+
+    /// Finds the stored chat matching `chat` (by id, or by name for id-less
+    /// chats), refreshing its name/type from the newer export, or adds it.
+    fn chat_index(&mut self, chat: Chat) -> u32 {
+        let existing = self
+            .data
+            .chats
+            .iter()
+            .position(|c| c.id == chat.id && (chat.id.is_some() || c.name == chat.name));
+        match existing {
+            Some(n) => {
+                self.data.chats[n] = chat;
+                n as u32
+            }
+            None => {
+                self.data.chats.push(chat);
+                (self.data.chats.len() - 1) as u32
+            }
+        }
+    }
+}
+
+/// An update wins only if it was edited after the version already indexed.
+fn is_newer(candidate: Option<i64>, current: Option<i64>) -> bool {
+    match (candidate, current) {
+        (Some(c), Some(cur)) => c > cur,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Writes `value` as JSON to `path` through a temp file and a rename.
+fn write_atomically(path: &Path, value: &impl Serialize) -> Result<(), Box<dyn Error>> {
+    let tmp = path.with_extension("json.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp).map_err(AnalyzerError::write(&tmp))?);
+    serde_json::to_writer(&mut writer, value).map_err(|e| AnalyzerError::write(&tmp)(e.into()))?;
+    writer.flush().map_err(AnalyzerError::write(&tmp))?;
+    drop(writer);
+    fs::rename(&tmp, path).map_err(AnalyzerError::write(path))?;
+    Ok(())
+}
+
+/// The distinct three-character windows of `token` (none if it is shorter):
+/// a token containing another has all of its trigrams.
+fn trigrams(token: &str) -> Vec<String> {
+    let chars: Vec<char> = token.chars().collect();
+    let mut trigrams: Vec<String> = chars.windows(3).map(|w| w.iter().collect()).collect();
+    trigrams.sort();
+    trigrams.dedup();
+    trigrams
+}
+
+fn message_terms(msg: &Message) -> HashSet<String> {
+    let mut terms: HashSet<String> = tokenize(&msg.text).flat_map(|t| trigrams(&t)).collect();
+    if let Some(from_id) = &msg.from_id {
+        terms.insert(format!("from:{}", from_id));
+    }
+    if let Some(from) = &msg.from {
+        terms.insert(format!("from:{}", from));
+    }
+    if msg.has_media() {
+        terms.insert("has:media".to_string());
+    }
+    if msg.has_link() {
+        terms.insert("has:link".to_string());
+    }
+    if msg.is_forwarded() {
+        terms.insert("is:forwarded".to_string());
+    }
+    if let Some(reply_to) = msg.reply_to_message_id {
+        terms.insert(format!("reply:{}", reply_to));
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A fresh folder under the temp dir, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("index-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn export(path: &str, messages: Value) {
+        let data = json!({ "name": "Chat", "type": "private_group", "id": 1, "messages": messages });
+        fs::write(path, data.to_string()).unwrap();
+    }
+
+    fn message(id: i64, text: &str, edited: Option<&str>) -> Value {
+        json!({ "id": id, "type": "message", "date": "2024-01-31T10:00:00", "edited": edited, "text": text })
+    }
+
+    fn texts(index: &Index) -> Vec<(i64, String)> {
+        index.load_all().unwrap().into_iter().map(|m| (m.id, m.text)).collect()
+    }
+
+    #[test]
+    fn re_adding_an_export_only_adds_new_and_edited_messages() {
+        let tmp = TempDir::new("update");
+        let (dir, first, second) = (tmp.path("index"), tmp.path("first.json"), tmp.path("second.json"));
+        export(&first, json!([message(1, "hello there", None), message(2, "teh typo", None)]));
+        export(
+            &second,
+            json!([
+                message(1, "hello there", None),
+                message(2, "the typo", Some("2024-02-01T00:00:00")),
+                message(3, "brand new", None),
+            ]),
+        );
+
+        let mut index = Index::open_or_create(&dir).unwrap();
+        let stats = index.add_export(&first, ParseMode::Lenient).unwrap();
+        assert_eq!((stats.added, stats.updated, stats.unchanged), (2, 0, 0));
+        index.save().unwrap();
+        let first_postings = fs::read(tmp.0.join("index").join("postings-0.json")).unwrap();
+
+        let mut index = Index::open(&dir).unwrap();
+        let stats = index.add_export(&second, ParseMode::Lenient).unwrap();
+        assert_eq!((stats.added, stats.updated, stats.unchanged), (1, 1, 1));
+        index.save().unwrap();
+
+        // The update went to a postings file of its own; the first one is untouched
+        assert_eq!(fs::read(tmp.0.join("index").join("postings-0.json")).unwrap(), first_postings);
+        let index = Index::open(&dir).unwrap();
+        assert_eq!(index.live_count(), 3);
+        let expected = [(1, "hello there"), (2, "the typo"), (3, "brand new")].map(|(id, t)| (id, t.to_string()));
+        assert_eq!(texts(&index), expected);
+
+        // Adding the same export again changes nothing
+        let mut index = Index::open(&dir).unwrap();
+        let stats = index.add_export(&second, ParseMode::Lenient).unwrap();
+        assert_eq!((stats.added, stats.updated, stats.unchanged), (0, 0, 3));
+        assert_eq!(index.live_count(), 3);
+    }
+
+    #[test]
+    fn an_older_edit_doesnt_replace_a_newer_one() {
+        let tmp = TempDir::new("older-edit");
+        let (dir, newer, older) = (tmp.path("index"), tmp.path("newer.json"), tmp.path("older.json"));
+        export(&newer, json!([message(1, "final", Some("2024-02-02T00:00:00"))]));
+        export(&older, json!([message(1, "draft", Some("2024-02-01T00:00:00"))]));
+
+        let mut index = Index::open_or_create(&dir).unwrap();
+        index.add_export(&newer, ParseMode::Lenient).unwrap();
+        let stats = index.add_export(&older, ParseMode::Lenient).unwrap();
+        assert_eq!((stats.added, stats.updated, stats.unchanged), (0, 0, 1));
+        assert_eq!(texts(&index), [(1, "final".to_string())]);
+    }
+
+    #[test]
+    fn candidates_cover_messages_added_by_later_updates() {
+        let tmp = TempDir::new("candidates");
+        let (dir, first, second) = (tmp.path("index"), tmp.path("first.json"), tmp.path("second.json"));
+        export(&first, json!([message(1, "apples and pears", None)]));
+        export(&second, json!([message(2, "pineapple juice", None)]));
+
+        let mut index = Index::open_or_create(&dir).unwrap();
+        index.add_export(&first, ParseMode::Lenient).unwrap();
+        index.save().unwrap();
+        index.add_export(&second, ParseMode::Lenient).unwrap();
+        index.save().unwrap();
+
+        let index = Index::open(&dir).unwrap();
+        let found = |query: &str| {
+            let docs = index.candidates(&MessageFilter::default(), Some(query)).unwrap().unwrap();
+            let docs: Vec<u32> = docs.into_iter().collect();
+            let mut ids: Vec<i64> = index.read_many(&docs).unwrap().values().map(|m| m.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(found("apple"), [1, 2]);
+        assert_eq!(found("juice"), [2]);
+        assert_eq!(found("pears"), [1]);
+    }
+}
@@ -0,0 +1,79 @@
+// src/lib.rs
+
+//! Analyses of Telegram JSON exports, as a library. The `telegram-analyzer`
+//! CLI is a thin wrapper around it.
+//!
+//!   - [`loader`] reads one or more exports (or an index built by [`index`])
+//!     into the typed model of [`model`]: [`Message`] and [`Chat`]. A
+//!     lenient load (the default) keeps malformed messages and tallies them ([`quality`]).
+//!   - [`filter`] selects the messages to analyze; [`Context`] loads exports
+//!     once per run with the filter and timezone applied, and replaces
+//!     identities with keyed pseudonyms when asked to ([`pseudonym`]).
+//!   - Each module of [`behaviors`] has a function that computes its analysis
+//!     from messages (or a folder or file) and returns a typed result, e.g.
+//!     [`behaviors::count_daily::count_daily`] → [`behaviors::count_daily::DailyCounts`].
+//!     Every result converts into a [`Report`], the table all writers share.
+//!     [`plugin`]s add behaviors from external executables; [`pii`] finds
+//!     the personal data that `detect-pii` counts.
+//!   - [`Report::write`] and [`Report::render`] write a report in any
+//!     [`OutputFormat`]; [`html`] and [`markdown`] render combined reports,
+//!     [`charts`] activity charts, and [`sqlite`] a SQLite database.
+//!   - [`progress`] draws progress bars for long loads and runs, and times
+//!     their phases (wall time and peak heap) for `--timings`.
+//!   - Errors are `Box<dyn Error>`; the failures worth telling apart (a missing
+//!     or malformed input, an unreadable file, a failed write) are an
+//!     [`AnalyzerError`] inside, with a hint and an exit code.
+//!
+//! ```no_run
+//! use telegram_analyzer::behaviors::{count_daily, text_stats};
+//! use telegram_analyzer::loader::load_messages;
+//! use telegram_analyzer::report::Report;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let messages = load_messages(&["result.json".to_string()])?;
+//! let days = count_daily::count_daily(&messages, chrono_tz::UTC);
+//! for day in &days.days {
+//!     println!("{}: {}", day.date, day.count);
+//! }
+//!
+//! let stats = text_stats::text_stats(&messages, 10, 200.0);
+//! let report = Report::from(&stats);
+//! report.render(&telegram_analyzer::OutputFormat::Csv, &mut std::io::stdout())?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod behavior;
+pub mod behaviors;
+pub mod charts;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+pub mod common;
+pub mod config;
+pub mod context;
+pub mod dump;
+pub mod error;
+pub mod filter;
+pub mod html;
+pub mod index;
+pub mod loader;
+pub mod markdown;
+pub mod merge;
+pub mod model;
+pub mod pii;
+pub mod plugin;
+pub mod progress;
+pub mod pseudonym;
+pub mod quality;
+pub mod query;
+pub mod report;
+pub mod sqlite;
+
+pub use behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, DynBehavior};
+pub use common::{ChartFormat, OutputFormat, ReportFormat};
+pub use context::Context;
+pub use error::AnalyzerError;
+pub use filter::MessageFilter;
+pub use loader::load_messages;
+pub use model::{Chat, Message};
+pub use report::Report;
@@ -0,0 +1,215 @@
+// src/loader.rs
+
+use crate::error::AnalyzerError;
+use crate::index::{self, Index};
+use crate::merge::merge_exports;
+use crate::model::{Chat, Message};
+use crate::progress;
+use crate::quality::{self, DataQuality, ParseMode, Problem};
+use serde_json::Value;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+/// One chat of an export with its messages still as raw JSON objects.
+pub struct ExportChat {
+    pub chat: Chat,
+    pub messages: Vec<Value>,
+}
+
+/// Messages returned by [`load`], with the malformed ones among them.
+pub struct Loaded {
+    pub messages: Vec<Message>,
+    pub quality: DataQuality,
+}
+
+/// Loads every message of one or more Telegram JSON exports into the typed model.
+///
+///   - A single path may also be an index directory built by the `index`
+///     subcommand, in which case the indexed messages are loaded instead.
+///   - Several paths are merged: overlapping exports of the same chat are
+///     deduplicated by `(chat, message id)`, keeping the latest edit.
+///
+/// Messages are returned chat by chat, in export order (ordered by id when
+/// merged). Each message records the export it came from in `source`.
+/// Entries without a numeric `id` are skipped. Malformed messages (see
+/// [`quality::problem`]) fail a `Strict` load and are kept, and tallied in
+/// `quality`, by a `Lenient` one. An index holds the messages that were
+/// kept when it was built.
+pub fn load(paths: &[String], mode: ParseMode) -> Result<Loaded, Box<dyn Error>> {
+    let mut quality = DataQuality::default();
+    let messages = match paths {
+        [] => return Err(AnalyzerError::NoInput { flag: "--json" }.into()),
+        [path] if index::is_index(path) => {
+            let messages = Index::open(path)?.load_all()?;
+            quality.messages = messages.len();
+            messages
+        }
+        [path] => load_export(path, mode, &mut quality)?,
+        _ => {
+            if let Some(path) = paths.iter().find(|p| index::is_index(p)) {
+                return Err(AnalyzerError::Usage(format!(
+                    "{} is an index; add the other exports to it with `index` instead of combining them",
+                    path
+                ))
+                .into());
+            }
+            let sources: Vec<Arc<str>> = paths.iter().map(|p| Arc::from(p.as_str())).collect();
+            let (chats, _) = merge_exports(paths)?;
+            let total = chats.iter().map(|c| c.messages.len() as u64).sum();
+            let bar = progress::count(&format!("parse {} exports", paths.len()), total);
+            let mut messages = Vec::new();
+            for merged in chats {
+                let chat = Arc::new(merged.chat);
+                for m in &merged.messages {
+                    messages.extend(normalize(&m.value, &chat, &sources[m.source], mode, &mut quality)?);
+                    bar.inc(1);
+                }
+            }
+            bar.finish_and_clear();
+            messages
+        }
+    };
+    Ok(Loaded { messages, quality })
+}
+
+/// [`load`] in the default `Lenient` mode, for the messages alone.
+pub fn load_messages(paths: &[String]) -> Result<Vec<Message>, Box<dyn Error>> {
+    Ok(load(paths, ParseMode::default())?.messages)
+}
+
+fn load_export(path: &str, mode: ParseMode, quality: &mut DataQuality) -> Result<Vec<Message>, Box<dyn Error>> {
+    let source: Arc<str> = Arc::from(path);
+    let chats = read_chats(path)?;
+    let total = chats.iter().map(|c| c.messages.len() as u64).sum();
+    let bar = progress::count(&format!("parse {}", file_name(path)), total);
+    let mut messages = Vec::new();
+    for export_chat in chats {
+        let chat = Arc::new(export_chat.chat);
+        for item in &export_chat.messages {
+            messages.extend(normalize(item, &chat, &source, mode, quality)?);
+            bar.inc(1);
+        }
+    }
+    bar.finish_and_clear();
+    Ok(messages)
+}
+
+/// The file name of `path`, for progress bars.
+fn file_name(path: &str) -> String {
+    Path::new(path).file_name().map_or(path.into(), |n| n.to_string_lossy()).into_owned()
+}
+
+/// Normalizes one message of the export at `source`: `None` without an id.
+/// A malformed message fails a `Strict` load and is tallied in `quality` by a
+/// `Lenient` one.
+pub(crate) fn normalize(
+    value: &Value,
+    chat: &Arc<Chat>,
+    source: &Arc<str>,
+    mode: ParseMode,
+    quality: &mut DataQuality,
+) -> Result<Option<Message>, AnalyzerError> {
+    let Some(mut msg) = Message::from_json(value, Arc::clone(chat)) else {
+        return Ok(None);
+    };
+    quality.messages += 1;
+    match (quality::problem(value, &msg), mode) {
+        (None, _) => {}
+        (Some(reason), ParseMode::Lenient) => quality.flag(reason, msg.id),
+        (Some(Problem::BadDate), ParseMode::Strict) => {
+            let date = ["date", "date_unixtime"]
+                .iter()
+                .find_map(|key| value.get(*key).filter(|d| !d.is_null()))
+                .unwrap_or(&Value::Null);
+            return Err(AnalyzerError::BadTimestamp {
+                path: source.to_string(),
+                message_id: msg.id,
+                value: date.as_str().map(str::to_string).unwrap_or_else(|| date.to_string()),
+            });
+        }
+        (Some(reason), ParseMode::Strict) => {
+            return Err(AnalyzerError::MalformedMessage {
+                path: source.to_string(),
+                message_id: msg.id,
+                reason,
+            })
+        }
+    }
+    msg.source = Some(Arc::clone(source));
+    Ok(Some(msg))
+}
+
+/// Reads an export and splits it into chats without normalizing messages.
+///
+/// Accepted shapes:
+///   - a single-chat export: `{ "name", "type", "id", "messages": [...] }`,
+///   - a full-account export: `{ "chats": { "list": [ <chat>, ... ] }, "left_chats": ... }`,
+///   - a bare top-level array of message objects (one chat without id or name).
+pub fn read_chats(path: &str) -> Result<Vec<ExportChat>, Box<dyn Error>> {
+    let p = Path::new(path);
+    if !p.is_file() {
+        return Err(AnalyzerError::MissingInput {
+            what: "Input file",
+            path: path.to_string(),
+        }
+        .into());
+    }
+
+    let file = File::open(p).map_err(AnalyzerError::io(p))?;
+    let size = file.metadata().map_err(AnalyzerError::io(p))?.len();
+    let bar = progress::bytes(&format!("read {}", file_name(path)), size);
+    let reader = BufReader::new(bar.wrap_read(file));
+    let data: Value = serde_json::from_reader(reader).map_err(|e| AnalyzerError::json(p, e))?;
+    bar.finish_and_clear();
+    let unsupported = |reason: &str| AnalyzerError::UnsupportedSchema {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let mut chats = Vec::new();
+    match data {
+        Value::Array(items) => chats.push(ExportChat {
+            chat: Chat::default(),
+            messages: items,
+        }),
+        Value::Object(mut obj) if obj.contains_key("messages") => {
+            let messages = match obj.remove("messages") {
+                Some(Value::Array(items)) => items,
+                _ => return Err(unsupported("expected \"messages\" to be a JSON array").into()),
+            };
+            chats.push(ExportChat {
+                chat: Chat::from_json(&Value::Object(obj)),
+                messages,
+            });
+        }
+        Value::Object(mut obj) if obj.contains_key("chats") || obj.contains_key("left_chats") => {
+            for key in ["chats", "left_chats"] {
+                let list = match obj.get_mut(key).and_then(|c| c.get_mut("list")) {
+                    Some(Value::Array(list)) => std::mem::take(list),
+                    _ => continue,
+                };
+                for mut chat in list {
+                    let messages = match chat.get_mut("messages") {
+                        Some(Value::Array(items)) => std::mem::take(items),
+                        _ => Vec::new(),
+                    };
+                    chats.push(ExportChat {
+                        chat: Chat::from_json(&chat),
+                        messages,
+                    });
+                }
+            }
+        }
+        _ => {
+            return Err(unsupported(
+                "expected an object with \"messages\" or \"chats\", or a top-level JSON array",
+            )
+            .into())
+        }
+    }
+
+    Ok(chats)
+}
@@ -1,146 +1,268 @@
-use clap::{Parser, Subcommand};
+use clap::builder::EnumValueParser;
+use clap::{Arg, ArgAction, ArgMatches, Command, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::env;
 use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
 
-mod common;
-use common::OutputFormat;
-
-mod behavior;
-use behavior::{Behavior, BehaviorType};
-
-mod behaviors;
-use behaviors::*;
+use telegram_analyzer::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorType, Input};
+use telegram_analyzer::behaviors::{self, Script, Search, SearchFormat, SearchOptions};
+use telegram_analyzer::common::{ChartFormat, OutputFormat, ReportFormat};
+use telegram_analyzer::config::{self, BehaviorConfig, Config};
+use telegram_analyzer::context::Context;
+use telegram_analyzer::error::{exit_code, AnalyzerError};
+use telegram_analyzer::filter::MessageFilter;
+use telegram_analyzer::index::Index;
+use telegram_analyzer::merge::{merge_exports, write_export};
+use telegram_analyzer::model::Message;
+use telegram_analyzer::pseudonym::{self, Pseudonymizer};
+use telegram_analyzer::quality::{DataQuality, ParseMode};
+use telegram_analyzer::{charts, dump, html, markdown, plugin, progress, report, sqlite};
 
 /// CLI entrypoint—Clap automatically provides `--help`.
 #[derive(Parser)]
 #[command(name = "telegram-analyzer")]
 #[command(about = "A CLI tool to analyze Telegram JSON, file metadata, etc.", long_about = None)]
+#[command(subcommand_required = true, arg_required_else_help = true)]
+#[command(after_help = EXIT_CODES)]
 struct Cli {
+    /// `None` for a behavior's own subcommand (see `behavior_command`)
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+
+    /// Message filters, applied once when an export is loaded and shared by every behavior.
+    #[command(flatten)]
+    filter: MessageFilter,
+
+    /// (Optional) TOML config file; by default `telegram-analyzer.toml` in the current folder,
+    /// then `$XDG_CONFIG_HOME/telegram-analyzer/config.toml`. Command-line options win over it.
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    /// (Optional) Apply the config file's `[profile.<name>]` section.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// (Optional) Timezone for time slots, days and charts, e.g. “Europe/Berlin” (default: UTC).
+    #[arg(long, global = true, value_parser = config::parse_timezone)]
+    timezone: Option<chrono_tz::Tz>,
+
+    /// (Optional) Fail on the first malformed message (missing or bad date, unknown type,
+    /// truncated text) instead of keeping it and reporting it as `data_quality`.
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// (Optional) Worker threads for running behaviors side by side and for the heavy
+    /// aggregations (default: one per CPU core; 1 runs everything in turn).
+    #[arg(long, global = true, value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// (Optional) Print the wall time and peak memory of each phase (loading, each
+    /// behavior, writing) to stderr when done.
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// (Optional) Replace sender ids, names and usernames with stable pseudonyms (HMAC of
+    /// --pseudonym-key) in every output, so reports can be shared without identities.
+    #[arg(long, global = true)]
+    pseudonymize: bool,
+
+    /// (Optional) Key of --pseudonymize (default: $TELEGRAM_ANALYZER_PSEUDONYM_KEY); the same
+    /// key gives the same pseudonyms.
+    #[arg(long, global = true, value_name = "KEY")]
+    pseudonym_key: Option<String>,
+
+    /// (Optional) With --pseudonymize, also replace phone numbers and emails in message text
+    /// with [phone] and [email], and mentions with pseudonyms.
+    #[arg(long, global = true)]
+    scrub_text: bool,
 }
 
-/// All the subcommands correspond to individual Behaviors (or aggregators).
+/// Subcommands besides the behaviors' own, which are generated from
+/// `behaviors::registry()` (see `behavior_command`).
 #[derive(Subcommand)]
 enum Commands {
-    /// Extract URLs from JSON and print each one
-    ExtractUrls {
-        /// Path to input JSON
-        #[arg(short, long)]
-        json: String,
-    },
+    /// Search message text (literal or regex) and print each match with optional context
+    Search {
+        /// Text to look for (a regular expression with --regex)
+        query: String,
 
-    /// Extract all URLs and print each URL with the number of times it appeared
-    CountUrls {
-        /// Path to the file to scan for URLs.
-        #[arg(short, long)]
-        input: String,
+        /// Path to input JSON (repeat to merge several exports), or an index directory built by `index`
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
 
-        /// (Optional) Where to save the URL,count pairs. If omitted, prints to stdout.
+        #[command(flatten)]
+        options: SearchOptions,
+
+        /// (Optional) Folder to save `search_results.*` in. If omitted, prints to stdout.
         #[arg(short, long)]
         output: Option<String>,
 
-        /// (Optional) Output format: “stdout” (default), “txt”, or “csv”.
+        /// (Optional) Output format: “text” (default), “json”, “jsonl”, “csv”, “tsv”, or “markdown”.
         #[arg(short, long, value_enum)]
-        format: Option<OutputFormat>,
+        format: Option<SearchFormat>,
     },
 
-    /// From JSON, count how many events occurred in each 30-minute slot (e.g. 13:30–14:00)
-    CountTimeSlots {
-        /// Path to the file to scan for URLs.
-        #[arg(short, long)]
-        input: String,
+    /// Run a Rhai script over the messages and write the rows it emits
+    /// (needs `--features script`)
+    #[command(after_help = behaviors::script::API)]
+    Script {
+        /// The script to run, e.g. `analysis.rhai`
+        #[arg(long)]
+        file: String,
+
+        /// Path to input JSON (repeat to merge several exports), or an index directory built by `index`
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
 
-        /// (Optional) Where to save the slot,count pairs. If omitted, prints to stdout.
+        /// (Optional) Folder to save `script_results.*` in. If omitted, prints to stdout.
         #[arg(short, long)]
         output: Option<String>,
 
-        /// (Optional) Output format: “stdout” (default), “txt”, or “csv”.
+        /// (Optional) Output format (default: stdout); every other format needs --output.
         #[arg(short, long, value_enum)]
         format: Option<OutputFormat>,
-    },
 
-    /// From JSON, count how many events occurred each day
-    CountDaily {
-        /// Path to input JSON
-        #[arg(short, long)]
-        json: String,
+        /// (Optional) Stop a script that runs more operations than this (0: no limit).
+        #[arg(long, value_name = "N", default_value_t = behaviors::script::DEFAULT_MAX_OPERATIONS)]
+        max_operations: u64,
     },
 
-    /// From a folder, get all file extensions and print a list of occurrences per extension
-    ListExtensions {
-        /// Path to folder
-        #[arg(short, long)]
-        folder: String,
-    },
+    /// List every behavior with its type, inputs, options and output columns
+    ListBehaviors,
 
-    /// From a single file, get metadata (name, format, size) and print it
-    FileMetadata {
-        /// Path to file
-        #[arg(short, long)]
-        file: String,
-    },
+    /// Build or update an on-disk search index from a JSON export.
+    /// Re-running it with a newer export of the same chat only adds new or edited messages.
+    /// The index directory can then be passed anywhere a JSON path is expected.
+    Index {
+        /// Path to input JSON (repeat to merge several exports)
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
 
-    /// From JSON, get all interactions of each user and print id, num_messages, media & links shared, unique links, name/username changes, last_seen
-    UserInteractions {
-        /// Path to input JSON
+        /// Index directory (created if missing)
         #[arg(short, long)]
-        json: String,
+        index: String,
     },
 
-    /// From JSON, get total number of messages sent and number of distinct users
-    MessageStats {
-        /// Path to input JSON
+    /// Merge several exports of the same chat(s) into one Telegram-compatible `result.json`.
+    /// Messages are deduplicated by (chat id, message id), keeping the latest edit;
+    /// each one records its export in an `export_source` field. Global filters apply.
+    Merge {
+        /// Path to input JSON (repeat for each export to merge)
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
+
+        /// Folder to write the merged `result.json` in
         #[arg(short, long)]
-        json: String,
+        output: String,
     },
 
-    /// From JSON, output which users shared which media files and links & how many times each link was shared
-    Diffusion {
-        /// Path to input JSON
+    /// Write the whole normalized message table (after global filters) to
+    /// `<output>/messages.parquet`, for DuckDB, pandas and friends.
+    /// Needs a build with `--features arrow` unless another --format is chosen.
+    Dump {
+        /// Path to input JSON (repeat to merge several exports), or an index directory
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
+
+        /// Folder to write `messages.<ext>` in
         #[arg(short, long)]
-        json: String,
+        output: String,
+
+        /// (Optional) Output format: “parquet” (default), “arrow”, or any other table format.
+        #[arg(short, long, value_enum)]
+        format: Option<OutputFormat>,
     },
 
-    /// From JSON, get who sent which link and who sent which media
-    Shares {
-        /// Path to input JSON
+    /// Draw the activity charts (after global filters) into `<output>`:
+    /// messages per 30-minute slot, messages per day, a weekday × hour heatmap
+    /// and messages per day stacked by the top senders.
+    Charts {
+        /// Path to input JSON (repeat to merge several exports), or an index directory
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
+
+        /// Folder to write the `<chart>.<ext>` files in
         #[arg(short, long)]
-        json: String,
+        output: String,
+
+        /// (Optional) Image format: “svg” (default) or “png” (needs `--features png`).
+        #[arg(short, long, value_enum)]
+        format: Option<ChartFormat>,
     },
 
-    /// From JSON, get number of words, characters, sentences, paragraphs, reading_time, word_counter, average word length, num messages with media
-    TextStats {
-        /// Path to input JSON
+    /// Write the parsed export(s) into a SQLite database `<output>/export.sqlite`:
+    /// chats, users, messages, entities, links, media, reactions and replies,
+    /// plus one `report_*` table per behavior (a failing behavior is left out and
+    /// fails the run once the database is written). Global filters apply.
+    ExportSqlite {
+        /// Path to input JSON (repeat to merge several exports), or an index directory
+        #[arg(short, long, required = true)]
+        json: Vec<String>,
+
+        /// Folder to write `export.sqlite` in
         #[arg(short, long)]
-        json: String,
+        output: String,
+
+        /// (Optional) Folder to also store the list-extensions report for
+        #[arg(long)]
+        folder: Option<String>,
+
+        /// (Optional) File to also store the file-metadata report for
+        #[arg(long)]
+        file: Option<String>,
     },
 
-    /// Run *all* analyses. Requires:
+    /// Run *all* analyses (or the config file's `behaviors`), given here or in the config file:
     ///   --json <path>  (for JSON-based behaviors)
     ///   --folder <path> (for folder-based behaviors)
     ///   --file <path>  (for file-based behaviors)
+    /// Behaviors whose input is missing are skipped; failures don't stop the others.
     All {
-        /// JSON file path
+        /// JSON file path (repeat to merge several exports)
         #[arg(short, long)]
-        json: String,
+        json: Vec<String>,
         /// Folder path
         #[arg(short, long)]
-        folder: String,
+        folder: Option<String>,
         /// Single file path
+        #[arg(long)]
+        file: Option<String>,
+
+        /// (Optional) Folder to save the results in. If omitted, prints to stdout.
         #[arg(short, long)]
-        file: String,
+        output: Option<String>,
+
+        /// (Optional) Output format: “stdout” (default), “txt”, “csv”, “tsv”, “json”, “jsonl”, “markdown”, “parquet”, or “arrow”.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// (Optional) Write one combined report instead of printing each result:
+        /// “html” (`report.html`) or “markdown” (`report.md`), into --output (or the current folder).
+        #[arg(long, value_enum)]
+        report: Option<ReportFormat>,
+
+        /// (Optional) Markdown template for `--report markdown` (see `markdown::render` for placeholders)
+        #[arg(long, requires = "report")]
+        template: Option<String>,
     },
 
     /// Run *all* behaviors of a specific BehaviorType.
-    /// You must pass (--json / --folder / --file) for those behaviors that need it.
+    /// Pass (--json / --folder / --file) for those behaviors that need it; the others are skipped.
     Group {
         /// BehaviorType to run (ValueEnum lets Clap validate it)
         #[arg(value_enum)]
         behavior_type: BehaviorType,
 
-        /// (Optional) path to JSON (for JSON-based behaviors)
+        /// (Optional) path to JSON (for JSON-based behaviors; repeat to merge several exports)
         #[arg(long)]
-        json: Option<String>,
+        json: Vec<String>,
 
         /// (Optional) path to folder (for folder-based behaviors)
         #[arg(long)]
@@ -149,99 +271,806 @@ enum Commands {
         /// (Optional) path to file (for file-metadata)
         #[arg(long)]
         file: Option<String>,
+
+        /// (Optional) Folder to save the results in. If omitted, prints to stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// (Optional) Output format: “stdout” (default), “txt”, “csv”, “tsv”, “json”, “jsonl”, “markdown”, “parquet”, or “arrow”.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// (Optional) Write one combined report instead of printing each result:
+        /// “html” (`report.html`) or “markdown” (`report.md`), into --output (or the current folder).
+        #[arg(long, value_enum)]
+        report: Option<ReportFormat>,
+
+        /// (Optional) Markdown template for `--report markdown` (see `markdown::render` for placeholders)
+        #[arg(long, requires = "report")]
+        template: Option<String>,
     },
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let cli = Cli::parse();
+/// Resolves `--format` (default `stdout`); every other format writes a file
+/// and so needs an `--output` folder.
+fn output_format(output: &Option<String>, format: Option<OutputFormat>) -> Result<OutputFormat, Box<dyn Error>> {
+    let fmt = format.unwrap_or(OutputFormat::Stdout);
+    if fmt != OutputFormat::Stdout && output.is_none() {
+        return Err(AnalyzerError::Usage("--format not ‘stdout’ requires --output <folder>".to_string()).into());
+    }
+    Ok(fmt)
+}
+
+/// Inputs and outputs of an `all`/`group` run: the command line's, else the config file's.
+struct RunSettings {
+    json: Vec<String>,
+    folder: Option<String>,
+    file: Option<String>,
+    output: Option<String>,
+    format: Option<OutputFormat>,
+    report: Option<ReportFormat>,
+    template: Option<String>,
+    behavior: HashMap<String, BehaviorConfig>,
+}
 
-    match cli.command {
-        Commands::ExtractUrls { json } => {
-            let b = ExtractUrls::new(json);
-            b.run()?;
+impl RunSettings {
+    /// Whether `input` was given (on the command line or in the config file).
+    fn has(&self, input: Input) -> bool {
+        match input {
+            Input::Json => !self.json.is_empty(),
+            Input::Folder => self.folder.is_some(),
+            Input::File => self.file.is_some(),
         }
+    }
 
-        Commands::CountUrls { input, output, format } => {
-            let fmt = format.unwrap_or(OutputFormat::Stdout);
-            // If user requested Txt or Csv, output folder must be provided
-            if (fmt != OutputFormat::Stdout) && output.is_none() {
-                return Err("Error: --format not ‘stdout’ requires --output <folder>".into());
+    /// Fills in what the command line left unset from `config`.
+    fn with_config(self, config: &Config) -> Result<Self, Box<dyn Error>> {
+        for name in config.behavior.keys().chain(config.behaviors.iter().flatten()) {
+            let Some(info) = behaviors::find(name).filter(|info| info.build.is_some()) else {
+                let known: Vec<&str> = runnable().map(|info| info.name).collect();
+                return Err(AnalyzerError::Usage(format!(
+                    "Unknown behavior “{}” in the config file (known: {})",
+                    name,
+                    known.join(", ")
+                ))
+                .into());
+            };
+            for option in config.behavior.get(name).into_iter().flat_map(|b| b.options.keys()) {
+                if !info.options.iter().any(|o| o.name == option) {
+                    let message = format!("Unknown option “{}” for {} in the config file", option, name);
+                    return Err(AnalyzerError::Usage(message).into());
+                }
             }
-            println!("→ Running CountUrls on file: {}", input);
-            let b = CountUrls::new(input, output, Some(fmt));
-            b.run()?;
         }
+        Ok(RunSettings {
+            json: if self.json.is_empty() { config.json.clone() } else { self.json },
+            folder: self.folder.or_else(|| config.folder.clone()),
+            file: self.file.or_else(|| config.file.clone()),
+            output: self.output.or_else(|| config.output.clone()),
+            format: self.format.or_else(|| config.format.clone()),
+            report: self.report.or(config.report),
+            template: self.template.or_else(|| config.template.clone()),
+            behavior: config.behavior.clone(),
+        })
+    }
+}
+
+/// One behavior of an `all`/`group` run, with what to build it from and
+/// where its result goes.
+struct Planned {
+    info: &'static BehaviorInfo,
+    args: BehaviorArgs,
+    output: Option<String>,
+    format: OutputFormat,
+}
+
+/// The behaviors `all` runs (and `group` picks from): those the registry can build.
+fn runnable() -> impl Iterator<Item = &'static BehaviorInfo> {
+    behaviors::registry().iter().copied().filter(|info| info.build.is_some())
+}
+
+/// Plans every behavior of `all`/`group`, each writing to the run's output
+/// unless its `[behavior.<name>]` table says otherwise.
+fn plan_behaviors(settings: &RunSettings) -> Result<Vec<Planned>, Box<dyn Error>> {
+    let mut planned = Vec::new();
+    for info in runnable() {
+        let cfg = settings.behavior.get(info.name).cloned().unwrap_or_default();
+        let output = cfg.output.clone().or_else(|| settings.output.clone());
+        let format = output_format(&output, cfg.format.clone().or_else(|| settings.format.clone()))?;
+        planned.push(Planned {
+            info,
+            args: BehaviorArgs {
+                json: settings.json.clone(),
+                folder: settings.folder.clone(),
+                file: settings.file.clone(),
+                options: cfg.option_values(),
+            },
+            output,
+            format,
+        });
+    }
+    Ok(planned)
+}
 
-        Commands::CountTimeSlots { input, output, format } => {
-            let fmt = format.unwrap_or(OutputFormat::Stdout);
-            // If user requested Txt or Csv, output folder must be provided
-            if (fmt != OutputFormat::Stdout) && output.is_none() {
-                return Err("Error: --format not ‘stdout’ requires --output <folder>".into());
+/// How one behavior of an `all`/`group` run ended.
+enum Outcome {
+    Succeeded,
+    Skipped(String),
+    /// The error message and its exit code
+    Failed(String, u8),
+}
+
+/// An `all`/`group` (or `export-sqlite`) run in which some behaviors failed (each was reported as it failed).
+#[derive(Debug)]
+struct RunFailed {
+    failed: usize,
+    total: usize,
+    /// The highest exit code of the failures
+    code: u8,
+}
+
+impl fmt::Display for RunFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} behaviors failed", self.failed, self.total)
+    }
+}
+
+impl Error for RunFailed {}
+
+/// Runs the behaviors of `all`/`group`. Without a report format each prints or
+/// writes its own result; with one, results are collected into one combined
+/// report (and still written one by one when their format is a file format).
+///
+/// The behaviors run side by side on the thread pool (see `--threads`) and
+/// share the messages `ctx` loads once; their results are written in plan
+/// order, so the output doesn't depend on which finishes first.
+///
+/// Behaviors missing one of their inputs are skipped with a notice, and a
+/// failing behavior doesn't stop the others; a summary of what succeeded,
+/// was skipped or failed (and why) closes the run on stderr. The run is an
+/// error (`RunFailed`) if any behavior failed.
+fn run_behaviors(ctx: &Context, planned: Vec<Planned>, settings: RunSettings) -> Result<(), Box<dyn Error>> {
+    // 1) Skip the behaviors without their inputs
+    let skipped: Vec<Option<String>> = planned
+        .iter()
+        .map(|p| {
+            let missing: Vec<&str> = p
+                .info
+                .inputs
+                .iter()
+                .filter(|input| !settings.has(**input))
+                .map(Input::flag)
+                .collect();
+            let reason = (!missing.is_empty()).then(|| format!("no {} given", missing.join(" or ")));
+            if let Some(reason) = &reason {
+                eprintln!("→ Skipping {}: {}", p.info.name, reason);
+            }
+            reason
+        })
+        .collect();
+
+    // 2) Load the exports they share up front (a failed load is left to each behavior to report)
+    let runs = skipped.iter().filter(|s| s.is_none()).count();
+    if planned.iter().zip(&skipped).any(|(p, s)| s.is_none() && p.info.inputs.contains(&Input::Json)) {
+        let _ = ctx.messages(&settings.json);
+    }
+
+    // 3) Build and run them side by side, on the messages they share
+    let failure = |e: Box<dyn Error>| (e.to_string(), error_code(e.as_ref()));
+    let bar = progress::count("behaviors", runs as u64);
+    let results: Vec<Option<Result<report::Report, (String, u8)>>> =
+        progress::phase(&format!("run {} behaviors", runs), || {
+            planned
+                .par_iter()
+                .zip(&skipped)
+                .map(|(p, skipped)| {
+                    let build = p.info.build.expect("all/group only plan buildable behaviors");
+                    if skipped.is_some() {
+                        return None;
+                    }
+                    let result = progress::concurrent_phase(&format!("run {}", p.info.name), || {
+                        build(p.info, &p.args).and_then(|b| b.report(ctx)).map_err(failure)
+                    });
+                    bar.inc(1);
+                    bar.set_message(p.info.name);
+                    Some(result)
+                })
+                .collect()
+        });
+    bar.finish_and_clear();
+
+    // 4) Write the results in plan order, carrying on after a failure
+    let mut outcomes: Vec<(&str, Outcome)> = Vec::new();
+    let mut reports = Vec::new();
+    progress::phase("write", || {
+        for ((Planned { info, output, format, .. }, skipped), result) in planned.into_iter().zip(skipped).zip(results) {
+            let Some(result) = result else {
+                outcomes.push((info.name, Outcome::Skipped(skipped.unwrap_or_default())));
+                continue;
+            };
+            let written = result.and_then(|r| {
+                match settings.report {
+                    None => write_result(&r, &format, output.as_deref()).map_err(failure)?,
+                    Some(_) => {
+                        if format != OutputFormat::Stdout {
+                            write_result(&r, &format, output.as_deref()).map_err(failure)?;
+                        }
+                        reports.push(r);
+                    }
+                }
+                Ok(())
+            });
+            match written {
+                Ok(()) => outcomes.push((info.name, Outcome::Succeeded)),
+                Err((reason, code)) => {
+                    eprintln!("→ {} failed: {}", info.name, reason);
+                    outcomes.push((info.name, Outcome::Failed(reason, code)));
+                }
             }
-            println!("→ Running CountTimeSlots on JSON: {}", input);
-            let b = CountTimeSlots::new(input, output, Some(fmt));
-            b.run()?;
         }
+    });
 
-        Commands::CountDaily { json } => {
-            let b = CountDaily::new(json);
-            b.run()?;
+    // 5) The malformed messages a lenient load kept (if any), then the combined report of whatever succeeded
+    let format = output_format(&settings.output, settings.format.clone())?;
+    if let Some(quality) = write_data_quality(ctx, &format, settings.output.as_deref())? {
+        if settings.report.is_some() {
+            reports.push(quality);
         }
+    }
+    let written = match settings.report {
+        Some(format) => progress::phase("write report", || write_report(ctx, &reports, format, &settings)),
+        None => Ok(()),
+    };
 
-        Commands::ListExtensions { folder } => {
-            let b = ListExtensions::new(folder);
-            b.run()?;
+    // 6) Summary
+    let count = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|(_, o)| f(o)).count();
+    let failed = count(|o| matches!(o, Outcome::Failed(..)));
+    eprintln!(
+        "→ Summary: {} succeeded, {} skipped, {} failed",
+        count(|o| matches!(o, Outcome::Succeeded)),
+        count(|o| matches!(o, Outcome::Skipped(_))),
+        failed
+    );
+    for (name, outcome) in &outcomes {
+        match outcome {
+            Outcome::Succeeded => eprintln!("   succeeded  {}", name),
+            Outcome::Skipped(reason) => eprintln!("   skipped    {} ({})", name, reason),
+            Outcome::Failed(reason, _) => eprintln!("   failed     {} ({})", name, reason),
         }
+    }
 
-        Commands::FileMetadata { file } => {
-            let b = FileMetadata::new(file);
-            b.run()?;
+    written?;
+    if failed > 0 {
+        let code = outcomes.iter().filter_map(|(_, o)| match o {
+            Outcome::Failed(_, code) => Some(*code),
+            _ => None,
+        });
+        return Err(RunFailed {
+            failed,
+            total: outcomes.len(),
+            code: code.max().unwrap_or(exit_code::FAILURE),
         }
+        .into());
+    }
+    Ok(())
+}
+
+/// Writes one result in `format` (to stdout, or into the `output` folder with a notice).
+fn write_result(report: &report::Report, format: &OutputFormat, output: Option<&str>) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = report.write(format, output)? {
+        println!("{} (saved to {})", report.summary_line, path.display());
+    }
+    Ok(())
+}
+
+/// In lenient mode, reports the malformed messages the run's loads kept, if any: as `data_quality.<ext>`
+/// in the `output` folder for file formats, else as a table on stderr (stdout
+/// keeps to the results). Returns the report for a combined `--report`.
+fn write_data_quality(
+    ctx: &Context,
+    format: &OutputFormat,
+    output: Option<&str>,
+) -> Result<Option<report::Report>, Box<dyn Error>> {
+    let malformed = |quality: &DataQuality| ctx.mode == ParseMode::Lenient && quality.malformed_total() > 0;
+    let Some(quality) = ctx.data_quality().filter(malformed) else {
+        return Ok(None);
+    };
+    let report = report::Report::from(&quality);
+    match format {
+        OutputFormat::Stdout => report.render(format, &mut io::stderr().lock())?,
+        _ => write_result(&report, format, output)?,
+    }
+    Ok(Some(report))
+}
+
+/// Writes the combined `--report` document of an `all`/`group` run into its output folder.
+fn write_report(
+    ctx: &Context,
+    reports: &[report::Report],
+    format: ReportFormat,
+    settings: &RunSettings,
+) -> Result<(), Box<dyn Error>> {
+    let output = settings.output.as_deref();
+    let inputs = &settings.json;
+
+    // Activity charts go with the time-based behaviors (which loaded the messages already)
+    let timed = reports
+        .iter()
+        .any(|r| matches!(r.behavior, "count-time-slots" | "count-daily"));
+    let charts = match ctx.messages(inputs) {
+        Ok(messages) if timed => charts::activity_charts(&messages, ctx.timezone),
+        _ => Vec::new(),
+    };
 
-        Commands::UserInteractions { json } => {
-            let b = UserInteractions::new(json);
-            b.run()?;
+    let (document, extension) = match format {
+        ReportFormat::Html => (html::render(reports, &charts, REPORT_TITLE, inputs), "html"),
+        ReportFormat::Markdown => {
+            let template = match &settings.template {
+                Some(path) => fs::read_to_string(path).map_err(AnalyzerError::io(path))?,
+                None => markdown::DEFAULT_TEMPLATE.to_string(),
+            };
+            // Charts are written as SVG files next to the report
+            let mut images = Vec::new();
+            if markdown::uses_charts(&template) && !charts.is_empty() {
+                let dir = PathBuf::from(output.unwrap_or(".")).join("charts");
+                charts::write_charts(&charts, ChartFormat::Svg, &dir.to_string_lossy())?;
+                images = charts
+                    .iter()
+                    .map(|c| (c.title.clone(), format!("charts/{}.svg", c.name)))
+                    .collect();
+            }
+            (markdown::render(reports, &images, &template, REPORT_TITLE, inputs)?, "md")
         }
+    };
+    let (path, mut out) = report::create_output(output, "report", extension)?;
+    out.write_all(document.as_bytes())
+        .and_then(|_| out.flush())
+        .map_err(AnalyzerError::write(&path))?;
+    println!("→ Report: {} sections written to {}", reports.len(), path.display());
+    Ok(())
+}
+
+/// Title of the combined `--report` documents.
+const REPORT_TITLE: &str = "Telegram export analysis";
+
+/// The full command line: the derived `Cli` plus one subcommand per registry
+/// behavior, with the `all`/`group` help listing what they run.
+fn cli_command() -> Command {
+    // Behaviors first in the help, as `all` runs them
+    let mut cmd = Cli::command();
+    let derived: Vec<String> = cmd.get_subcommands().map(|c| c.get_name().to_string()).collect();
+    for (n, name) in derived.iter().enumerate() {
+        cmd = cmd.mut_subcommand(name, |c| c.display_order(behaviors::registry().len() + n));
+    }
+    for (n, info) in runnable().enumerate() {
+        cmd = cmd.subcommand(behavior_command(info).display_order(n));
+    }
+
+    let mut all_help = String::from("Behaviors (details with `list-behaviors`):\n");
+    for info in runnable() {
+        all_help.push_str(&format!("  {:<18} {}\n", info.name, info.description));
+    }
+    all_help.push_str(&format!(
+        "\nPlugins are loaded from ${} and {}/plugins.\n",
+        plugin::PLUGIN_PATH_VAR,
+        config::config_dir().map_or("<config folder>".to_string(), |d| d.display().to_string())
+    ));
+    let mut group_help = String::from("Behavior types:\n");
+    for info in runnable() {
+        group_help.push_str(&format!("  {:<18} {}\n", type_name(info.behavior_type), info.name));
+    }
+    cmd.mut_subcommand("all", |c| c.after_help(all_help))
+        .mut_subcommand("group", |c| c.after_help(group_help))
+}
 
-        Commands::MessageStats { json } => {
-            let b = MessageStats::new(json);
-            b.run()?;
+/// A behavior's own subcommand: an argument per input, `--output`, `--format`
+/// and one `--<option>` per declared option.
+fn behavior_command(info: &'static BehaviorInfo) -> Command {
+    let mut cmd = Command::new(info.name).about(info.description);
+    // -f is --folder or --file when a behavior takes only one of them, else --format
+    let paths = info.inputs.iter().filter(|i| **i != Input::Json).count();
+    for input in info.inputs {
+        let arg = match input {
+            Input::Json => Arg::new("json")
+                .short('j')
+                .long("json")
+                .value_name("JSON")
+                .visible_alias("input")
+                .short_alias('i')
+                .action(ArgAction::Append)
+                .required(true)
+                .help("Path to input JSON (repeat to merge several exports), or an index directory"),
+            Input::Folder => Arg::new("folder")
+                .long("folder")
+                .value_name("FOLDER")
+                .required(true)
+                .help("Path to folder"),
+            Input::File => Arg::new("file")
+                .long("file")
+                .value_name("FILE")
+                .required(true)
+                .help("Path to file"),
+        };
+        cmd = cmd.arg(if *input != Input::Json && paths == 1 { arg.short('f') } else { arg });
+    }
+    let format = Arg::new("format")
+        .long("format")
+        .value_name("FORMAT")
+        .value_parser(EnumValueParser::<OutputFormat>::new())
+        .help("(Optional) Output format: “stdout” (default), “txt”, “csv”, “tsv”, “json”, “jsonl”, “markdown”, “parquet”, or “arrow”.");
+    let format = if paths == 1 { format } else { format.short('f') };
+    cmd = cmd
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("OUTPUT")
+                .help("(Optional) Folder to save the results in. If omitted, prints to stdout."),
+        )
+        .arg(format);
+    for option in info.options {
+        cmd = cmd.arg(Arg::new(option.name).long(option.name).help(option.help));
+    }
+    cmd
+}
+
+/// Runs a behavior's own subcommand. Options not given fall back to the
+/// config file's `[behavior.<name>]` table.
+fn run_behavior_command(
+    ctx: &Context,
+    config: &Config,
+    info: &'static BehaviorInfo,
+    matches: &ArgMatches,
+) -> Result<(), Box<dyn Error>> {
+    let value = |id: &str| matches.try_get_one::<String>(id).ok().flatten().cloned();
+    let output = value("output");
+    let format = output_format(&output, matches.get_one::<OutputFormat>("format").cloned())?;
+    let mut options = config.behavior.get(info.name).map(BehaviorConfig::option_values).unwrap_or_default();
+    for option in info.options {
+        if let Some(v) = value(option.name) {
+            options.insert(option.name.to_string(), v);
         }
+    }
+    let args = BehaviorArgs {
+        json: matches
+            .try_get_many::<String>("json")
+            .ok()
+            .flatten()
+            .map(|paths| paths.cloned().collect())
+            .unwrap_or_default(),
+        folder: value("folder"),
+        file: value("file"),
+        options,
+    };
+    let build = info.build.expect("generated subcommands are buildable");
+    let report = progress::phase(&format!("run {}", info.name), || {
+        let spinner = progress::spinner(info.name);
+        let report = build(info, &args)?.report(ctx);
+        spinner.finish_and_clear();
+        report
+    })?;
+    progress::phase("write", || {
+        write_result(&report, &format, output.as_deref())?;
+        write_data_quality(ctx, &format, output.as_deref())
+    })?;
+    Ok(())
+}
 
-        Commands::Diffusion { json } => {
-            let b = Diffusion::new(json);
-            b.run()?;
+/// `list-behaviors`: every registry entry with what it declares.
+fn list_behaviors() {
+    for info in behaviors::registry() {
+        println!("{} ({})", info.name, type_name(info.behavior_type));
+        println!("    {}", info.description);
+        let inputs: Vec<&str> = info.inputs.iter().map(Input::flag).collect();
+        println!("    inputs:  {}", inputs.join(", "));
+        for option in info.options {
+            println!("    option:  --{}  {}", option.name, option.help);
+        }
+        let columns: Vec<String> = info
+            .columns
+            .iter()
+            .map(|c| format!("{}: {}", c.name, c.kind.as_str()))
+            .collect();
+        if columns.is_empty() {
+            println!("    output:  {} v{} (columns set at run time)", info.file_stem, info.schema_version);
+        } else {
+            println!("    output:  {} v{} ({})", info.file_stem, info.schema_version, columns.join(", "));
+        }
+        if info.build.is_none() {
+            println!("    (own subcommand only; not run by all/group)");
         }
+        if let Some(plugin) = plugin::plugins().iter().find(|p| p.info.name == info.name) {
+            println!("    plugin:  {}", plugin.path.display());
+        }
+    }
+}
 
-        Commands::Shares { json } => {
-            let b = Shares::new(json);
-            b.run()?;
+/// A `BehaviorType` as typed on the command line (`url-count`).
+fn type_name(kind: BehaviorType) -> String {
+    kind.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+}
+
+/// Exit codes, listed under `--help`.
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  unexpected failure
+  2  invalid command line or config file
+  3  bad input: missing, malformed or not a Telegram JSON export
+  4  an input can't be read
+  5  an output can't be written";
+
+/// The exit code for `e`: an `AnalyzerError`'s own, the highest of a failed run's, else 1.
+fn error_code(e: &(dyn Error + 'static)) -> u8 {
+    if let Some(e) = e.downcast_ref::<AnalyzerError>() {
+        e.exit_code()
+    } else if let Some(e) = e.downcast_ref::<RunFailed>() {
+        e.code
+    } else {
+        exit_code::FAILURE
+    }
+}
+
+/// Counts heap use for the peaks of `--timings`.
+#[global_allocator]
+static ALLOCATOR: progress::PeakAlloc = progress::PeakAlloc;
+
+fn main() -> ExitCode {
+    let result = run();
+    if let Some(phases) = progress::timings() {
+        let report = report::Report::from(phases.as_slice());
+        if let Err(e) = report.render(&OutputFormat::Stdout, &mut io::stderr().lock()) {
+            eprintln!("Error: {}", e);
         }
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if let Some(hint) = e.downcast_ref::<AnalyzerError>().and_then(AnalyzerError::hint) {
+                eprintln!("  hint: {}", hint);
+            }
+            ExitCode::from(error_code(e.as_ref()))
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    // Plugins add subcommands, so they are loaded before the command line is parsed
+    // Plugin options become arguments next to the global ones, so they can't share a name
+    let global: Vec<String> = Cli::command()
+        .get_arguments()
+        .flat_map(|arg| [Some(arg.get_id().as_str()), arg.get_long()])
+        .flatten()
+        .map(String::from)
+        .collect();
+    plugin::load(&plugin::search_path(), &global);
+    let matches = cli_command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    progress::enable(io::stdout().is_terminal());
+    if cli.timings {
+        progress::enable_timings();
+    }
+    let config = Config::load(cli.config.as_deref(), cli.profile.as_deref())?;
+    let mut filter = cli.filter;
+    config.filter.apply(&mut filter)?;
+    let timezone = match cli.timezone {
+        Some(tz) => tz,
+        None => config.timezone()?.unwrap_or(chrono_tz::UTC),
+    };
+    let mode = if cli.strict || config.strict { ParseMode::Strict } else { ParseMode::Lenient };
+    let mut ctx = Context::new(filter, timezone, mode);
+    if cli.pseudonymize || config.pseudonymize {
+        let key = cli
+            .pseudonym_key
+            .or_else(|| env::var(pseudonym::KEY_VAR).ok())
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| {
+                AnalyzerError::Usage(format!("--pseudonymize needs a key: --pseudonym-key or ${}", pseudonym::KEY_VAR))
+            })?;
+        ctx = ctx.with_pseudonymizer(Pseudonymizer::new(key.as_bytes(), cli.scrub_text || config.scrub_text));
+    } else if cli.scrub_text || config.scrub_text {
+        return Err(AnalyzerError::Usage("--scrub-text only works with --pseudonymize".to_string()).into());
+    }
+    if let Some(threads) = cli.threads.or(config.threads) {
+        rayon::ThreadPoolBuilder::new().num_threads(threads as usize).build_global()?;
+    }
+
+    let Some(command) = cli.command else {
+        let (name, sub) = matches.subcommand().expect("a subcommand is required");
+        let info = behaviors::find(name).expect("generated subcommands come from the registry");
+        return run_behavior_command(&ctx, &config, info, sub);
+    };
 
-        Commands::TextStats { json } => {
-            let b = TextStats::new(json);
-            b.run()?;
+    match command {
+        Commands::Search {
+            query,
+            json,
+            options,
+            output,
+            format,
+        } => {
+            let format = format.unwrap_or(SearchFormat::Text);
+            let results = Search::new(json, query, options).run(&ctx)?;
+            match results.write(&format, output.as_deref())? {
+                Some(path) => println!(
+                    "→ Search: {} matches in {} messages, results saved to {}",
+                    results.hits.len(),
+                    results.searched,
+                    path.display()
+                ),
+                None if format == SearchFormat::Text => println!(
+                    "→ Search: {} matches in {} messages",
+                    results.hits.len(),
+                    results.searched
+                ),
+                None => {}
+            }
+            write_data_quality(&ctx, &OutputFormat::Stdout, None)?;
         }
 
-        Commands::All { json, folder, file } => {
-            // “All” implicitly uses stdout for CountUrls/CountTimeSlots,
-            // or their default behavior if output not needed.
-            let behaviors: Vec<Box<dyn Behavior>> = vec![
-                Box::new(ExtractUrls::new(json.clone())),
-                Box::new(CountUrls::new(json.clone(), None, Some(OutputFormat::Stdout))),
-                Box::new(CountTimeSlots::new(json.clone(), None, Some(OutputFormat::Stdout))),
-                Box::new(CountDaily::new(json.clone())),
-                Box::new(ListExtensions::new(folder.clone())),
-                Box::new(FileMetadata::new(file.clone())),
-                Box::new(UserInteractions::new(json.clone())),
-                Box::new(MessageStats::new(json.clone())),
-                Box::new(Diffusion::new(json.clone())),
-                Box::new(Shares::new(json.clone())),
-                Box::new(TextStats::new(json.clone())),
-            ];
-            for b in behaviors {
-                b.run()?;
+        Commands::Script {
+            file,
+            json,
+            output,
+            format,
+            max_operations,
+        } => {
+            let format = output_format(&output, format)?;
+            let result = Script::new(file, json).with_max_operations(max_operations).run(&ctx)?;
+            write_result(&report::Report::from(&result), &format, output.as_deref())?;
+            write_data_quality(&ctx, &format, output.as_deref())?;
+        }
+
+        Commands::ListBehaviors => list_behaviors(),
+
+        Commands::Index { json, index } => {
+            println!("→ Indexing {} into {}", json.join(", "), index);
+            let mut idx = Index::open_or_create(&index)?;
+            for path in &json {
+                let stats = idx.add_export(path, ctx.mode)?;
+                println!(
+                    "→ Index: {}: {} added, {} updated, {} unchanged",
+                    path, stats.added, stats.updated, stats.unchanged
+                );
+                if stats.quality.malformed_total() > 0 {
+                    report::Report::from(&stats.quality).render(&OutputFormat::Stdout, &mut io::stderr().lock())?;
+                }
             }
+            idx.save()?;
+            println!("→ Index: {} messages indexed", idx.live_count());
+        }
+
+        Commands::Merge { json, output } => {
+            if ctx.pseudonymizer().is_some() {
+                let message = "merge copies the exports' JSON as is, which --pseudonymize doesn't cover; \
+                               use dump for a pseudonymized copy";
+                return Err(AnalyzerError::Usage(message.to_string()).into());
+            }
+            let (mut chats, stats) = merge_exports(&json)?;
+            if !ctx.filter.is_empty() {
+                for merged in &mut chats {
+                    let chat = Arc::new(merged.chat.clone());
+                    merged.messages.retain(|m| {
+                        Message::from_json(&m.value, Arc::clone(&chat)).is_some_and(|msg| ctx.filter.matches(&msg))
+                    });
+                }
+            }
+            let path = write_export(&chats, &json, &output)?;
+            let kept: usize = chats.iter().map(|c| c.messages.len()).sum();
+            println!(
+                "→ Merge: read {} messages from {} exports ({} duplicates, {} replaced by a newer edit); wrote {} messages to {}",
+                stats.read,
+                json.len(),
+                stats.duplicates,
+                stats.replaced,
+                kept,
+                path.display()
+            );
+        }
+
+        Commands::Dump { json, output, format } => {
+            let messages = ctx.messages(&json)?;
+            let fmt = format.unwrap_or(OutputFormat::Parquet);
+            write_result(&dump::message_report(&messages), &fmt, Some(&output))?;
+            write_data_quality(&ctx, &fmt, Some(&output))?;
+        }
+
+        Commands::Charts { json, output, format } => {
+            let messages = ctx.messages(&json)?;
+            let paths = charts::write_charts(
+                &charts::activity_charts(&messages, ctx.timezone),
+                format.unwrap_or(ChartFormat::Svg),
+                &output,
+            )?;
+            println!("→ Charts: {} charts written to {}", paths.len(), output);
+            write_data_quality(&ctx, &OutputFormat::Stdout, None)?;
+        }
+
+        Commands::ExportSqlite {
+            json,
+            output,
+            folder,
+            file,
+        } => {
+            // Every behavior whose inputs are given, as with `all`; a failing one is
+            // left out (and reported), and the dataset tables are still written
+            let mut reports = Vec::new();
+            let mut failures: Vec<u8> = Vec::new();
+            let mut total = 0;
+            for info in runnable() {
+                let args = BehaviorArgs {
+                    json: json.clone(),
+                    folder: folder.clone(),
+                    file: file.clone(),
+                    options: config.behavior.get(info.name).map(BehaviorConfig::option_values).unwrap_or_default(),
+                };
+                let given = info.inputs.iter().all(|input| match input {
+                    Input::Json => true,
+                    Input::Folder => args.folder.is_some(),
+                    Input::File => args.file.is_some(),
+                });
+                let Some(build) = info.build.filter(|_| given) else {
+                    continue;
+                };
+                total += 1;
+                match build(info, &args).and_then(|b| b.report(&ctx)) {
+                    Ok(report) => reports.push(report),
+                    Err(e) => {
+                        eprintln!("→ {} failed: {}", info.name, e);
+                        failures.push(error_code(e.as_ref()));
+                    }
+                }
+            }
+
+            let messages = ctx.messages(&json)?;
+            // A lenient run keeps its malformed messages' tally as a table of its own
+            reports.extend(write_data_quality(&ctx, &OutputFormat::Stdout, None)?);
+            let path = PathBuf::from(&output).join("export.sqlite");
+            let stats = sqlite::export_sqlite(&messages, &reports, &path)?;
+            println!(
+                "→ ExportSqlite: {} messages, {} users, {} chats and {} reports written to {}",
+                stats.messages,
+                stats.users,
+                stats.chats,
+                stats.reports,
+                path.display()
+            );
+            if let Some(&code) = failures.iter().max() {
+                return Err(RunFailed {
+                    failed: failures.len(),
+                    total,
+                    code,
+                }
+                .into());
+            }
+        }
+
+        Commands::All {
+            json,
+            folder,
+            file,
+            output,
+            format,
+            report,
+            template,
+        } => {
+            // Every behavior writes its own file into the same output folder
+            let settings = RunSettings {
+                json,
+                folder,
+                file,
+                output,
+                format,
+                report,
+                template,
+                behavior: HashMap::new(),
+            }
+            .with_config(&config)?;
+            let mut planned = plan_behaviors(&settings)?;
+            if let Some(enabled) = &config.behaviors {
+                planned.retain(|p| enabled.iter().any(|name| name == p.info.name));
+            }
+            run_behaviors(&ctx, planned, settings)?;
         }
 
         Commands::Group {
@@ -249,35 +1078,26 @@ fn main() -> Result<(), Box<dyn Error>> {
             json,
             folder,
             file,
+            output,
+            format,
+            report,
+            template,
         } => {
             // Build all behaviors, but only run those matching behavior_type.
-            // For CountUrls/CountTimeSlots in Group, default to stdout.
-            let mut behaviors: Vec<Box<dyn Behavior>> = Vec::new();
-            behaviors.push(Box::new(ExtractUrls::new(json.clone().unwrap_or_default())));
-            behaviors.push(Box::new(CountUrls::new(
-                json.clone().unwrap_or_default(),
-                None,
-                Some(OutputFormat::Stdout),
-            )));
-            behaviors.push(Box::new(CountTimeSlots::new(
-                json.clone().unwrap_or_default(),
-                None,
-                Some(OutputFormat::Stdout),
-            )));
-            behaviors.push(Box::new(CountDaily::new(json.clone().unwrap_or_default())));
-            behaviors.push(Box::new(ListExtensions::new(folder.clone().unwrap_or_default())));
-            behaviors.push(Box::new(FileMetadata::new(file.clone().unwrap_or_default())));
-            behaviors.push(Box::new(UserInteractions::new(json.clone().unwrap_or_default())));
-            behaviors.push(Box::new(MessageStats::new(json.clone().unwrap_or_default())));
-            behaviors.push(Box::new(Diffusion::new(json.clone().unwrap_or_default())));
-            behaviors.push(Box::new(Shares::new(json.clone().unwrap_or_default())));
-            behaviors.push(Box::new(TextStats::new(json.clone().unwrap_or_default())));
-
-            for b in behaviors {
-                if b.behavior_type() == behavior_type {
-                    b.run()?;
-                }
+            let settings = RunSettings {
+                json,
+                folder,
+                file,
+                output,
+                format,
+                report,
+                template,
+                behavior: HashMap::new(),
             }
+            .with_config(&config)?;
+            let mut planned = plan_behaviors(&settings)?;
+            planned.retain(|p| p.info.behavior_type == behavior_type);
+            run_behaviors(&ctx, planned, settings)?;
         }
     }
 
@@ -0,0 +1,123 @@
+// src/markdown.rs
+
+use crate::report::Report;
+use chrono::{SecondsFormat, Utc};
+use std::collections::HashSet;
+use std::error::Error;
+
+/// Rows per table where the template doesn't place a report explicitly.
+const DEFAULT_ROWS: usize = 50;
+
+/// Used when no `--template` is given.
+pub const DEFAULT_TEMPLATE: &str = "# {{title}}
+
+_Generated {{generated}} from {{inputs}}._
+
+{{overview}}
+
+{{charts}}
+
+{{reports}}
+";
+
+/// Renders the reports of an `all`/`group` run as one Markdown document
+/// (GitHub-flavored tables, URLs as links) by filling in `template`.
+///
+/// Placeholders:
+///   - `{{title}}`, `{{generated}}` (UTC timestamp), `{{inputs}}` (the input paths),
+///   - `{{overview}}`: a bullet list of each report's summary line,
+///   - `{{charts}}`: the activity charts as images (`charts` holds each one's
+///     title and path relative to the report),
+///   - `{{report:<behavior>}}`: that behavior's section (heading, summary, full table),
+///   - `{{table:<behavior>}}`: just its table,
+///   - `{{reports}}`: the sections of every report not placed explicitly,
+///     each table cut at 50 rows.
+///
+/// Everything else in the template (a preamble, extra headings, notes) is
+/// copied as is. A placeholder naming a behavior that didn't run renders as
+/// nothing; an unknown placeholder is an error.
+pub fn render(
+    reports: &[Report],
+    charts: &[(String, String)],
+    template: &str,
+    title: &str,
+    inputs: &[String],
+) -> Result<String, Box<dyn Error>> {
+    let placed: HashSet<&str> = placeholders(template)
+        .filter_map(|p| p.strip_prefix("report:").or_else(|| p.strip_prefix("table:")))
+        .map(str::trim)
+        .collect();
+
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| "Unclosed {{ in the report template".to_string())?;
+        let key = rest[start + 2..start + end].trim();
+        out.push_str(&expand(key, reports, charts, &placed, title, inputs)?);
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn expand(
+    key: &str,
+    reports: &[Report],
+    charts: &[(String, String)],
+    placed: &HashSet<&str>,
+    title: &str,
+    inputs: &[String],
+) -> Result<String, Box<dyn Error>> {
+    let find = |behavior: &str| reports.iter().find(|r| r.behavior == behavior);
+    let mut buf = Vec::new();
+    match key.split_once(':') {
+        None => match key {
+            "title" => return Ok(title.to_string()),
+            "generated" => return Ok(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+            "inputs" => return Ok(inputs.iter().map(|p| format!("`{}`", p)).collect::<Vec<_>>().join(", ")),
+            "overview" => {
+                let lines: Vec<String> = reports
+                    .iter()
+                    .map(|r| format!("- {}", r.summary_line.trim_start_matches("→ ")))
+                    .collect();
+                return Ok(lines.join("\n"));
+            }
+            "charts" => {
+                let images: Vec<String> = charts.iter().map(|(t, path)| format!("![{}]({})", t, path)).collect();
+                return Ok(images.join("\n\n"));
+            }
+            "reports" => {
+                for report in reports.iter().filter(|r| !placed.contains(r.behavior)) {
+                    report.write_markdown(&mut buf, DEFAULT_ROWS)?;
+                    buf.push(b'\n');
+                }
+            }
+            _ => return Err(format!("Unknown placeholder {{{{{}}}}} in the report template", key).into()),
+        },
+        Some(("report", behavior)) => {
+            if let Some(report) = find(behavior.trim()) {
+                report.write_markdown(&mut buf, usize::MAX)?;
+            }
+        }
+        Some(("table", behavior)) => {
+            if let Some(report) = find(behavior.trim()) {
+                report.write_markdown_table(&mut buf, usize::MAX)?;
+            }
+        }
+        Some(_) => return Err(format!("Unknown placeholder {{{{{}}}}} in the report template", key).into()),
+    }
+    Ok(String::from_utf8(buf)?.trim_end().to_string())
+}
+
+/// Whether `template` has a `{{charts}}` placeholder, i.e. needs the chart files.
+pub fn uses_charts(template: &str) -> bool {
+    placeholders(template).any(|p| p == "charts")
+}
+
+/// The trimmed contents of every `{{...}}` in `template`.
+fn placeholders(template: &str) -> impl Iterator<Item = &str> {
+    template.split("{{").skip(1).filter_map(|part| part.split_once("}}")).map(|(key, _)| key.trim())
+}
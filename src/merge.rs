@@ -0,0 +1,243 @@
+// src/merge.rs
+
+use crate::error::AnalyzerError;
+use crate::loader::read_chats;
+use crate::model::{Chat, Message};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Extra per-message field naming the export a merged message was taken from.
+/// Telegram readers ignore unknown fields, so merged files stay compatible.
+pub const SOURCE_FIELD: &str = "export_source";
+
+/// A chat with its deduplicated messages, ordered by message id.
+pub struct MergedChat {
+    pub chat: Chat,
+    pub messages: Vec<MergedMessage>,
+}
+
+/// A raw message object, the index (into the merged paths) of its export,
+/// and its `edited` timestamp.
+pub struct MergedMessage {
+    pub value: Value,
+    pub source: usize,
+    pub edited: Option<DateTime<Utc>>,
+}
+
+/// Counts reported by [`merge_exports`].
+#[derive(Debug, Default)]
+pub struct MergeStats {
+    pub read: usize,
+    pub duplicates: usize,
+    pub replaced: usize,
+}
+
+/// Combines several exports of the same chat(s) into one dataset.
+///
+///   - Chats are matched by id (by name for chats without one).
+///   - Messages are deduplicated by `(chat, message id)`; of several copies
+///     the one with the latest `edited` timestamp wins, and on a tie the copy
+///     from the later export in `paths`.
+pub fn merge_exports(paths: &[String]) -> Result<(Vec<MergedChat>, MergeStats), Box<dyn Error>> {
+    let mut chats: Vec<MergedChat> = Vec::new();
+    // (chat, message id) -> position in that chat's messages
+    let mut positions: HashMap<(usize, i64), usize> = HashMap::new();
+    let mut stats = MergeStats::default();
+
+    for (source, path) in paths.iter().enumerate() {
+        for export_chat in read_chats(path)? {
+            // 1) Find (or add) the merged chat; newer exports refresh its name
+            let existing = chats.iter().position(|c| {
+                c.chat.id == export_chat.chat.id
+                    && (export_chat.chat.id.is_some() || c.chat.name == export_chat.chat.name)
+            });
+            let chat_idx = match existing {
+                Some(n) => {
+                    chats[n].chat = export_chat.chat;
+                    n
+                }
+                None => {
+                    chats.push(MergedChat {
+                        chat: export_chat.chat,
+                        messages: Vec::new(),
+                    });
+                    chats.len() - 1
+                }
+            };
+            let chat = Arc::new(chats[chat_idx].chat.clone());
+
+            // 2) Add or replace each message
+            for value in export_chat.messages {
+                let Some(msg) = Message::from_json(&value, Arc::clone(&chat)) else {
+                    continue;
+                };
+                stats.read += 1;
+                let merged = &mut chats[chat_idx].messages;
+                let entry = MergedMessage {
+                    value,
+                    source,
+                    edited: msg.edited,
+                };
+                match positions.get(&(chat_idx, msg.id)) {
+                    Some(&pos) => {
+                        stats.duplicates += 1;
+                        if msg.edited >= merged[pos].edited {
+                            if msg.edited > merged[pos].edited {
+                                stats.replaced += 1;
+                            }
+                            merged[pos] = entry;
+                        }
+                    }
+                    None => {
+                        positions.insert((chat_idx, msg.id), merged.len());
+                        merged.push(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    // 3) Order each chat by message id
+    for chat in &mut chats {
+        chat.messages.sort_by_key(|m| m.value.get("id").and_then(Value::as_i64));
+    }
+    Ok((chats, stats))
+}
+
+/// Writes merged chats as Telegram Desktop JSON into `<output_dir>/result.json`:
+/// a single-chat export for one chat, a `"chats": { "list": [...] }` export otherwise.
+/// Each message records its export in [`SOURCE_FIELD`] unless it already has one
+/// from an earlier merge.
+pub fn write_export(
+    chats: &[MergedChat],
+    paths: &[String],
+    output_dir: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let chat_json = |c: &MergedChat| -> Value {
+        let messages: Vec<Value> = c
+            .messages
+            .iter()
+            .map(|m| {
+                let mut value = m.value.clone();
+                if let Value::Object(obj) = &mut value {
+                    obj.entry(SOURCE_FIELD)
+                        .or_insert_with(|| Value::String(paths[m.source].clone()));
+                }
+                value
+            })
+            .collect();
+        let mut obj = Map::new();
+        if let Some(name) = &c.chat.name {
+            obj.insert("name".to_string(), json!(name));
+        }
+        if let Some(kind) = &c.chat.kind {
+            obj.insert("type".to_string(), json!(kind));
+        }
+        if let Some(id) = c.chat.id {
+            obj.insert("id".to_string(), json!(id));
+        }
+        obj.insert("messages".to_string(), Value::Array(messages));
+        Value::Object(obj)
+    };
+
+    let document = match chats {
+        [single] => chat_json(single),
+        _ => json!({ "chats": { "list": chats.iter().map(chat_json).collect::<Vec<_>>() } }),
+    };
+
+    fs::create_dir_all(output_dir).map_err(AnalyzerError::write(output_dir))?;
+    let mut output_path = PathBuf::from(output_dir);
+    output_path.push("result.json");
+    let mut writer = BufWriter::new(File::create(&output_path).map_err(AnalyzerError::write(&output_path))?);
+    serde_json::to_writer_pretty(&mut writer, &document)
+        .map_err(|e| AnalyzerError::write(&output_path)(e.into()))?;
+    writer.flush().map_err(AnalyzerError::write(&output_path))?;
+    Ok(output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a single-chat export into a temp file and returns its path.
+    fn export(name: &str, chat_id: i64, messages: Value) -> String {
+        let path = std::env::temp_dir().join(format!("merge-{}-{}.json", name, std::process::id()));
+        let data = json!({ "name": "Chat", "type": "private_group", "id": chat_id, "messages": messages });
+        fs::write(&path, data.to_string()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn message(id: i64, text: &str, edited: Option<&str>) -> Value {
+        json!({ "id": id, "type": "message", "date": "2024-01-31T10:00:00", "edited": edited, "text": text })
+    }
+
+    /// Merges the exports, then removes them; `(chat id, message id, text)` of every merged message.
+    fn merged(paths: &[String]) -> (Vec<(Option<i64>, i64, String)>, MergeStats) {
+        let result = merge_exports(paths);
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+        let (chats, stats) = result.unwrap();
+        let messages = chats
+            .iter()
+            .flat_map(|c| {
+                c.messages.iter().map(|m| {
+                    let id = m.value["id"].as_i64().unwrap();
+                    (c.chat.id, id, m.value["text"].as_str().unwrap().to_string())
+                })
+            })
+            .collect();
+        (messages, stats)
+    }
+
+    #[test]
+    fn overlapping_exports_keep_the_latest_edit() {
+        let older = export(
+            "older",
+            1,
+            json!([
+                message(1, "first", None),
+                message(2, "typo", None),
+                message(3, "edited twice", Some("2024-02-02T00:00:00")),
+            ]),
+        );
+        let newer = export(
+            "newer",
+            1,
+            json!([
+                message(2, "fixed", Some("2024-02-01T00:00:00")),
+                message(3, "edited once", Some("2024-02-01T00:00:00")),
+                message(4, "new", None),
+            ]),
+        );
+        let (messages, stats) = merged(&[older, newer]);
+        let texts: Vec<(i64, &str)> = messages.iter().map(|(_, id, text)| (*id, text.as_str())).collect();
+        // The newer export's copy of 3 is an older edit, so the first export's stays
+        assert_eq!(texts, [(1, "first"), (2, "fixed"), (3, "edited twice"), (4, "new")]);
+        assert_eq!((stats.read, stats.duplicates, stats.replaced), (6, 2, 1));
+    }
+
+    #[test]
+    fn a_tie_goes_to_the_later_export() {
+        let first = export("tie-first", 1, json!([message(1, "from the first", None)]));
+        let second = export("tie-second", 1, json!([message(1, "from the second", None)]));
+        let (messages, stats) = merged(&[first, second]);
+        assert_eq!(messages, [(Some(1), 1, "from the second".to_string())]);
+        assert_eq!((stats.duplicates, stats.replaced), (1, 0));
+    }
+
+    #[test]
+    fn the_same_id_in_another_chat_is_another_message() {
+        let a = export("chat-a", 1, json!([message(1, "in a", None)]));
+        let b = export("chat-b", 2, json!([message(1, "in b", None)]));
+        let (messages, stats) = merged(&[a, b]);
+        assert_eq!(messages, [(Some(1), 1, "in a".to_string()), (Some(2), 1, "in b".to_string())]);
+        assert_eq!(stats.duplicates, 0);
+    }
+}
@@ -0,0 +1,337 @@
+// src/model.rs
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{Arc, OnceLock};
+
+/// The chat a message belongs to. Shared (via `Arc`) by every message of that chat.
+///
+///   - Single-chat exports (`result.json` with a top-level `"messages"` array)
+///     carry `name`, `type` and `id` at the top level.
+///   - Full-account exports list every chat under `"chats": { "list": [...] }`.
+///   - A bare top-level array of messages has no chat information at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chat {
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+impl Chat {
+    /// Builds a `Chat` from the chat-level fields of an export object.
+    pub fn from_json(value: &Value) -> Self {
+        Chat {
+            id: value.get("id").and_then(Value::as_i64),
+            name: value.get("name").and_then(Value::as_str).map(str::to_string),
+            kind: value.get("type").and_then(Value::as_str).map(str::to_string),
+        }
+    }
+
+    /// Human-readable label: the chat name, else its id, else `"-"`.
+    pub fn label(&self) -> String {
+        match (&self.name, self.id) {
+            (Some(name), _) => name.clone(),
+            (None, Some(id)) => id.to_string(),
+            (None, None) => "-".to_string(),
+        }
+    }
+}
+
+/// Telegram distinguishes regular messages from service messages
+/// (joins, pins, title changes, …).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageKind {
+    Message,
+    Service,
+    Other(String),
+}
+
+impl MessageKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MessageKind::Message => "message",
+            MessageKind::Service => "service",
+            MessageKind::Other(other) => other,
+        }
+    }
+}
+
+/// A formatted span of a message text (`text_entities` in the export),
+/// e.g. a `link`, `text_link`, `mention` or `bold` run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEntity {
+    pub kind: String,
+    pub text: String,
+    pub href: Option<String>,
+}
+
+/// One reaction of a message (`reactions` in newer exports): `kind` is
+/// `emoji`, `custom_emoji` or `paid`; `emoji` holds the emoji itself, or the
+/// sticker's `document_id` for custom emoji.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reaction {
+    pub kind: String,
+    pub emoji: Option<String>,
+    pub count: i64,
+}
+
+/// A single message, normalized from the export's JSON.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: i64,
+    pub kind: MessageKind,
+    pub date: Option<DateTime<Utc>>,
+    pub edited: Option<DateTime<Utc>>,
+    pub from: Option<String>,
+    pub from_id: Option<String>,
+    pub text: String,
+    pub entities: Vec<TextEntity>,
+    pub forwarded_from: Option<String>,
+    pub reply_to_message_id: Option<i64>,
+    pub via_bot: Option<String>,
+    pub media_type: Option<String>,
+    pub file: Option<String>,
+    pub photo: Option<String>,
+    pub reactions: Vec<Reaction>,
+    pub chat: Arc<Chat>,
+    /// Path of the export this message was loaded from, when known.
+    pub source: Option<Arc<str>>,
+}
+
+impl Message {
+    /// Normalizes one message object. Returns `None` if it has no numeric `id`.
+    ///
+    /// Service messages name their author in `actor`/`actor_id` rather than
+    /// `from`/`from_id`; both are folded into `from`/`from_id`.
+    pub fn from_json(value: &Value, chat: Arc<Chat>) -> Option<Self> {
+        let id = value.get("id").and_then(Value::as_i64)?;
+        let kind = match value.get("type").and_then(Value::as_str) {
+            Some("message") | None => MessageKind::Message,
+            Some("service") => MessageKind::Service,
+            Some(other) => MessageKind::Other(other.to_string()),
+        };
+        let text_value = value.get("text").unwrap_or(&Value::Null);
+
+        Some(Message {
+            id,
+            kind,
+            date: parse_timestamp(value, "date", "date_unixtime"),
+            edited: parse_timestamp(value, "edited", "edited_unixtime"),
+            from: string_field(value, "from").or_else(|| string_field(value, "actor")),
+            from_id: string_field(value, "from_id").or_else(|| string_field(value, "actor_id")),
+            text: flatten_text(text_value),
+            entities: parse_entities(value, text_value),
+            forwarded_from: string_field(value, "forwarded_from"),
+            reply_to_message_id: value.get("reply_to_message_id").and_then(Value::as_i64),
+            via_bot: string_field(value, "via_bot"),
+            media_type: string_field(value, "media_type"),
+            file: string_field(value, "file"),
+            photo: string_field(value, "photo"),
+            reactions: parse_reactions(value),
+            chat,
+            source: None,
+        })
+    }
+
+    pub fn is_service(&self) -> bool {
+        self.kind == MessageKind::Service
+    }
+
+    /// Exports don't flag bot accounts, so this goes by ids, never by display
+    /// names: the message was sent by the other party of a `bot_chat`, whose
+    /// `from_id` is `user<chat id>`. A message posted via an inline bot
+    /// (`via_bot`) was still sent by its user.
+    pub fn is_bot(&self) -> bool {
+        match (self.chat.kind.as_deref(), self.chat.id, self.from_id.as_deref()) {
+            (Some("bot_chat"), Some(chat_id), Some(from_id)) => from_id == format!("user{}", chat_id),
+            _ => false,
+        }
+    }
+
+    pub fn is_forwarded(&self) -> bool {
+        self.forwarded_from.is_some()
+    }
+
+    /// True if the message carries a photo, a file or any other media attachment.
+    pub fn has_media(&self) -> bool {
+        self.media_type.is_some() || self.photo.is_some() || self.file.is_some()
+    }
+
+    /// Every URL in the message: `link` entities, `text_link` targets, and—if
+    /// the export has no entities for this message—URLs found in the plain text.
+    pub fn links(&self) -> Vec<String> {
+        let from_entities: Vec<String> = self
+            .entities
+            .iter()
+            .filter_map(|e| match e.kind.as_str() {
+                "link" => Some(e.text.clone()),
+                "text_link" => e.href.clone(),
+                _ => None,
+            })
+            .collect();
+        if !self.entities.is_empty() {
+            return from_entities;
+        }
+        url_regex()
+            .find_iter(&self.text)
+            .map(|m| m.as_str().to_string())
+            .collect()
+    }
+
+    pub fn has_link(&self) -> bool {
+        !self.links().is_empty()
+    }
+
+    /// Sender label for display: the name, else the id, else `"-"`.
+    pub fn sender_label(&self) -> &str {
+        self.from
+            .as_deref()
+            .or(self.from_id.as_deref())
+            .unwrap_or("-")
+    }
+}
+
+/// Regex used to find `http://`/`https://` URLs in plain text.
+pub fn url_regex() -> &'static Regex {
+    static URL_REGEX: OnceLock<Regex> = OnceLock::new();
+    URL_REGEX.get_or_init(|| Regex::new(r"https?://[A-Za-z0-9\./\?=\+_\-]+").unwrap())
+}
+
+/// A URL reduced to what identifies the page, so variants count as one: the
+/// scheme and host lowercased, without `www.`, a default port, the fragment,
+/// tracking parameters (`utm_*`, `fbclid`, `gclid`) or a trailing slash.
+/// Text that isn't a `scheme://` URL is returned as is.
+pub fn canonical_url(url: &str) -> String {
+    let url = url.trim();
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let scheme = scheme.to_lowercase();
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (authority, path_query) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+
+    let mut host = authority.to_lowercase();
+    if let Some(h) = host.strip_prefix("www.") {
+        host = h.to_string();
+    }
+    let default_port = match scheme.as_str() {
+        "http" => ":80",
+        "https" => ":443",
+        _ => "",
+    };
+    if let Some(h) = host.strip_suffix(default_port).filter(|_| !default_port.is_empty()) {
+        host = h.to_string();
+    }
+
+    let (path, query) = path_query.split_once('?').unwrap_or((path_query, ""));
+    let params: Vec<&str> = query
+        .split('&')
+        .filter(|p| {
+            let key = p.split('=').next().unwrap_or_default();
+            !p.is_empty() && !key.starts_with("utm_") && key != "fbclid" && key != "gclid"
+        })
+        .collect();
+    let mut canonical = format!("{}://{}{}", scheme, host, path.trim_end_matches('/'));
+    if !params.is_empty() {
+        canonical.push('?');
+        canonical.push_str(&params.join("&"));
+    }
+    canonical
+}
+
+/// Lowercased alphanumeric runs of `text`.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+/// Prefers the exact `*_unixtime` field; falls back to the ISO field, which is
+/// either RFC 3339 or (as Telegram Desktop writes it) a naive local timestamp
+/// that we read as UTC.
+fn parse_timestamp(value: &Value, iso_key: &str, unix_key: &str) -> Option<DateTime<Utc>> {
+    if let Some(secs) = value
+        .get(unix_key)
+        .and_then(|v| v.as_str().and_then(|s| s.parse::<i64>().ok()).or_else(|| v.as_i64()))
+    {
+        return DateTime::from_timestamp(secs, 0);
+    }
+    let iso = value.get(iso_key).and_then(Value::as_str)?;
+    parse_iso_timestamp(iso)
+}
+
+/// Parses `2024-01-31T12:00:00Z`-style RFC 3339 or naive `2024-01-31T12:00:00`.
+pub fn parse_iso_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+/// `text` is either a plain string or an array mixing strings and
+/// `{ "type": ..., "text": ... }` objects; concatenate all text runs.
+fn flatten_text(text: &Value) -> String {
+    match text {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(s) => Some(s.as_str()),
+                Value::Object(_) => part.get("text").and_then(Value::as_str),
+                _ => None,
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Reads `text_entities` when present (newer exports); otherwise derives the
+/// entities from the object runs of an array-form `text`.
+fn parse_entities(value: &Value, text: &Value) -> Vec<TextEntity> {
+    let source = match value.get("text_entities").and_then(Value::as_array) {
+        Some(entities) => entities,
+        None => match text.as_array() {
+            Some(parts) => parts,
+            None => return Vec::new(),
+        },
+    };
+    source
+        .iter()
+        .filter_map(|e| {
+            let kind = e.get("type").and_then(Value::as_str)?;
+            if kind == "plain" {
+                return None;
+            }
+            Some(TextEntity {
+                kind: kind.to_string(),
+                text: string_field(e, "text").unwrap_or_default(),
+                href: string_field(e, "href"),
+            })
+        })
+        .collect()
+}
+
+fn parse_reactions(value: &Value) -> Vec<Reaction> {
+    let Some(reactions) = value.get("reactions").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    reactions
+        .iter()
+        .filter_map(|r| {
+            Some(Reaction {
+                kind: string_field(r, "type")?,
+                emoji: string_field(r, "emoji").or_else(|| string_field(r, "document_id")),
+                count: r.get("count").and_then(Value::as_i64).unwrap_or(1),
+            })
+        })
+        .collect()
+}
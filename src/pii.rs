@@ -0,0 +1,327 @@
+// src/pii.rs
+
+use crate::model::url_regex;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// A kind of personal data that [`find`] looks for. When matches overlap,
+/// the earlier kind wins (a Luhn-valid card number isn't also a phone number).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PiiKind {
+    Email,
+    /// Checked with the IBAN mod-97 checksum.
+    Iban,
+    /// 13 to 19 digits passing the Luhn check.
+    Card,
+    /// Bitcoin (base58check or bech32), Ethereum and Tron addresses.
+    CryptoWallet,
+    /// IPv4 or IPv6.
+    IpAddress,
+    /// One or two capital letters and 6 to 8 digits, e.g. `X1234567`.
+    Passport,
+    /// See [`phone_regex`] and [`is_phone`].
+    Phone,
+}
+
+impl PiiKind {
+    pub const ALL: [PiiKind; 7] = [
+        PiiKind::Email,
+        PiiKind::Iban,
+        PiiKind::Card,
+        PiiKind::CryptoWallet,
+        PiiKind::IpAddress,
+        PiiKind::Passport,
+        PiiKind::Phone,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Iban => "iban",
+            PiiKind::Card => "card",
+            PiiKind::CryptoWallet => "crypto-wallet",
+            PiiKind::IpAddress => "ip-address",
+            PiiKind::Passport => "passport",
+            PiiKind::Phone => "phone",
+        }
+    }
+
+    fn regex(&self) -> &'static Regex {
+        static REGEXES: OnceLock<Vec<Regex>> = OnceLock::new();
+        let regexes = REGEXES.get_or_init(|| {
+            let pattern = |kind: &PiiKind| match kind {
+                PiiKind::Email => email_regex().as_str(),
+                PiiKind::Iban => r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b",
+                PiiKind::Card => r"\b\d(?:[ \-]?\d){12,18}\b",
+                PiiKind::CryptoWallet => concat!(
+                    r"\b(?:bc1[ac-hj-np-z02-9]{11,71}|[13][a-km-zA-HJ-NP-Z1-9]{25,34}",
+                    r"|T[a-km-zA-HJ-NP-Z1-9]{33}|0x[a-fA-F0-9]{40})\b"
+                ),
+                PiiKind::IpAddress => concat!(
+                    r"\b\d{1,3}(?:\.\d{1,3}){3}\b",
+                    r"|(?:\b[0-9A-Fa-f]{1,4})?(?::[0-9A-Fa-f]{0,4}){2,7}\b"
+                ),
+                PiiKind::Passport => r"\b[A-Z]{1,2}\d{6,8}\b",
+                PiiKind::Phone => phone_regex().as_str(),
+            };
+            PiiKind::ALL.iter().map(|kind| Regex::new(pattern(kind)).unwrap()).collect()
+        });
+        &regexes[*self as usize]
+    }
+
+    /// Whether a match of `regex` really is one.
+    fn check(&self, candidate: &str) -> bool {
+        match self {
+            PiiKind::Iban => iban_checksum(candidate),
+            PiiKind::Card => {
+                let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+                (13..=19).contains(&digits.len()) && luhn(&digits)
+            }
+            PiiKind::CryptoWallet => {
+                candidate.starts_with("0x") || candidate.starts_with("bc1") || base58check(candidate)
+            }
+            // a digit keeps out paths like `Vec::new` or `::add`
+            PiiKind::IpAddress => {
+                candidate.contains(|c: char| c.is_ascii_digit()) && candidate.parse::<IpAddr>().is_ok()
+            }
+            PiiKind::Phone => is_phone(candidate),
+            PiiKind::Email | PiiKind::Passport => true,
+        }
+    }
+
+    /// Digit-only kinds that URLs are full of (ids, timestamps) aren't looked for inside URLs.
+    fn outside_urls(&self) -> bool {
+        matches!(self, PiiKind::Card | PiiKind::Passport | PiiKind::Phone)
+    }
+}
+
+/// One piece of personal data found in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found<'a> {
+    pub kind: PiiKind,
+    pub value: &'a str,
+    pub span: Range<usize>,
+}
+
+/// The personal data in `text`, in text order. Each stretch of text counts
+/// once, as the first kind (in `PiiKind` order) it passes the check of.
+pub fn find(text: &str) -> Vec<Found<'_>> {
+    let urls: Vec<Range<usize>> = url_regex().find_iter(text).map(|m| m.range()).collect();
+    let overlaps = |spans: &[Range<usize>], span: &Range<usize>| {
+        spans.iter().any(|s| s.start < span.end && span.start < s.end)
+    };
+    let mut found: Vec<Found> = Vec::new();
+    let mut taken: Vec<Range<usize>> = Vec::new();
+    for kind in PiiKind::ALL {
+        for m in kind.regex().find_iter(text) {
+            let span = m.range();
+            if (kind.outside_urls() && overlaps(&urls, &span)) || overlaps(&taken, &span) || !kind.check(m.as_str()) {
+                continue;
+            }
+            taken.push(span.clone());
+            found.push(Found {
+                kind,
+                value: m.as_str(),
+                span,
+            });
+        }
+    }
+    found.sort_by_key(|f| f.span.start);
+    found
+}
+
+/// `value` with its letters and digits replaced by `*`, all but a quarter of
+/// them split between the start and the end, e.g. `+44 ** **** ***8`; values
+/// with fewer than 8 are masked whole. Punctuation stays.
+pub fn mask(value: &str) -> String {
+    let total = value.chars().filter(|c| c.is_alphanumeric()).count();
+    let keep = if total < 8 { 0 } else { total / 4 };
+    let (head, tail) = (keep.div_ceil(2), keep / 2);
+    let mut seen = 0;
+    value
+        .chars()
+        .map(|c| {
+            if !c.is_alphanumeric() {
+                return c;
+            }
+            seen += 1;
+            if seen <= head || seen + tail > total {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+pub fn email_regex() -> &'static Regex {
+    static EMAIL: OnceLock<Regex> = OnceLock::new();
+    EMAIL.get_or_init(|| Regex::new(r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}").unwrap())
+}
+
+/// Candidate phone numbers: 8 to 15 digits, optionally after a `+`, with
+/// single spaces, dots, dashes or parentheses between them. Check with [`is_phone`].
+pub fn phone_regex() -> &'static Regex {
+    static PHONE: OnceLock<Regex> = OnceLock::new();
+    PHONE.get_or_init(|| Regex::new(r"(?:\+\d|\b\d)(?:[ .()\-]?\d){7,14}\b").unwrap())
+}
+
+/// Whether a [`phone_regex`] match is likely a phone number: at least 9
+/// digits (8 after a `+`), and not a date like `2024-01-31`.
+pub fn is_phone(candidate: &str) -> bool {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    let date = DATE.get_or_init(|| Regex::new(r"^\d{4}[-./]\d{1,2}[-./]\d{1,2}").unwrap());
+    let digits = candidate.chars().filter(char::is_ascii_digit).count();
+    let enough = if candidate.starts_with('+') { digits >= 8 } else { digits >= 9 };
+    enough && !date.is_match(candidate)
+}
+
+/// The Luhn check of card numbers.
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2 == 1, d * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// The IBAN check: 15 to 34 characters whose rearranged digits are 1 mod 97.
+fn iban_checksum(candidate: &str) -> bool {
+    let iban: String = candidate.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+    let (head, tail) = iban.split_at(4);
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        remainder = if value < 10 { remainder * 10 + value } else { remainder * 100 + value } % 97;
+    }
+    remainder == 1
+}
+
+/// Whether `candidate` decodes from base58 to 25 bytes whose last 4 are the
+/// double SHA-256 checksum of the rest (legacy Bitcoin and Tron addresses).
+fn base58check(candidate: &str) -> bool {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    let mut bytes = [0u8; 25];
+    for c in candidate.bytes() {
+        let Some(mut carry) = ALPHABET.iter().position(|&a| a == c) else {
+            return false;
+        };
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as usize * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        if carry != 0 {
+            return false;
+        }
+    }
+    let (payload, checksum) = bytes.split_at(21);
+    Sha256::digest(Sha256::digest(payload))[..4] == *checksum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(number: &str) -> Vec<u32> {
+        number.chars().filter_map(|c| c.to_digit(10)).collect()
+    }
+
+    #[test]
+    fn iban_checksum_accepts_valid_ibans() {
+        for iban in [
+            "DE89 3704 0044 0532 0130 00",
+            "DE89370400440532013000",
+            // Letters count as two digits (W = 32, E = 14, ...)
+            "GB82 WEST 1234 5698 7654 32",
+            "FR14 2004 1010 0505 0001 3M02 606",
+            "NL91ABNA0417164300",
+        ] {
+            assert!(iban_checksum(iban), "{}", iban);
+        }
+    }
+
+    #[test]
+    fn iban_checksum_rejects_invalid_ibans() {
+        for iban in [
+            "DE89 3704 0044 0532 0130 01",
+            "GB82 WEST 1234 5698 7654 33",
+            "GB28 WEST 1234 5698 7654 32",
+            "NL91 ABNA 0417",
+            "DE89-3704-0044-0532-0130-00",
+        ] {
+            assert!(!iban_checksum(iban), "{}", iban);
+        }
+    }
+
+    #[test]
+    fn luhn_checks_card_numbers() {
+        for card in ["4111 1111 1111 1111", "5555555555554444", "378282246310005", "6011111111111117"] {
+            assert!(luhn(&digits(card)), "{}", card);
+        }
+        for card in ["4111 1111 1111 1112", "5555555555554445", "378282246310006", "1234567812345678"] {
+            assert!(!luhn(&digits(card)), "{}", card);
+        }
+        // Length is checked with the digits
+        assert!(!PiiKind::Card.check("4111 1111 1111"));
+        assert!(PiiKind::Card.check("4111-1111-1111-1111"));
+    }
+
+    #[test]
+    fn is_phone_needs_enough_digits() {
+        for phone in ["+44 20 7946 0958", "+1 (415) 555-2671", "+4915123456", "0151 2345 6789", "030.1234.5678"] {
+            assert!(is_phone(phone), "{}", phone);
+        }
+        for short in ["+1234567", "12345678", "555-1234"] {
+            assert!(!is_phone(short), "{}", short);
+        }
+    }
+
+    #[test]
+    fn is_phone_excludes_dates() {
+        for date in ["2024-01-31 10", "2024.01.31 1030", "2024-1-31 103000"] {
+            // Each is a whole candidate with enough digits, so only the date check rules it out
+            assert_eq!(phone_regex().find(date).map(|m| m.as_str()), Some(date));
+            assert!(!is_phone(date), "{}", date);
+        }
+        assert!(is_phone("2024 0131 10"));
+    }
+
+    #[test]
+    fn find_resolves_overlaps_and_skips_urls() {
+        let text = "Card 4111 1111 1111 1111, mail ann@example.com, see https://x.com/u/12345678901";
+        let found: Vec<(PiiKind, &str)> = find(text).iter().map(|f| (f.kind, f.value)).collect();
+        assert_eq!(found, [(PiiKind::Card, "4111 1111 1111 1111"), (PiiKind::Email, "ann@example.com")]);
+    }
+
+    #[test]
+    fn mask_keeps_at_most_a_quarter() {
+        assert_eq!(mask("+44 20 7946 0958"), "+44 ** **** ***8");
+        assert_eq!(mask("ann.smith@example.com"), "an*.*****@*******.*om");
+        assert_eq!(mask("4111 1111 1111 1111"), "41** **** **** **11");
+    }
+
+    #[test]
+    fn mask_hides_short_values_whole() {
+        assert_eq!(mask("a@b.io"), "*@*.**");
+        assert_eq!(mask("ann@ex.io"), "***@**.**");
+        assert_eq!(mask("10.0.0.1"), "**.*.*.*");
+        assert_eq!(mask("192.168.1.1"), "1**.***.*.1");
+    }
+}
@@ -0,0 +1,506 @@
+// src/plugin.rs
+
+use crate::behavior::{Behavior, BehaviorArgs, BehaviorInfo, BehaviorOption, BehaviorType, DynBehavior, Input};
+use crate::behaviors::REGISTRY;
+use crate::common::{leak, OutputFormat};
+use crate::config;
+use crate::context::Context;
+use crate::dump;
+use crate::error::AnalyzerError;
+use crate::model::{parse_iso_timestamp, Message};
+use crate::report::{Cell, Column, ColumnType, Report};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{ChildStdin, Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+/// Folders (or single executables) to load plugins from, separated like `PATH`.
+pub const PLUGIN_PATH_VAR: &str = "TELEGRAM_ANALYZER_PLUGINS";
+
+/// A plugin: an external executable adding a behavior, which then has its own
+/// subcommand, runs in `all` and `group` and shows in the help like any other.
+///
+/// It speaks JSON over stdin/stdout, in two calls:
+///
+///   - `<plugin> describe` prints its manifest, the `BehaviorInfo` of a
+///     built-in behavior as JSON:
+///     `{ "name", "description", "type", "inputs": ["json"], "options": [{ "name", "help" }],
+///        "file_stem", "columns": [{ "name", "type": "integer" }], "schema_version" }`.
+///     `type` (a `group` name, default `plugin`), `inputs` (`json`, `folder`,
+///     `file`), `options`, `file_stem` (default: the name) and
+///     `schema_version` (default 1) are optional;
+///     `name`, `file_stem` and option names are lowercase letters, digits,
+///     `_` and `-`; options can't reuse the name of another argument.
+///   - `<plugin> run` reads a header line
+///     `{ "behavior", "options", "folder", "file", "timezone", "messages": <count> }`
+///     and then, for plugins with a `json` input, the run's (filtered) messages
+///     as JSON Lines in the `dump` schema. It prints its result:
+///     `{ "rows": [{ <column>: <value> }], "summary": { ... }, "summary_line": "..." }`.
+///
+/// Anything the plugin writes to stderr goes to the user as is; a non-zero
+/// exit status fails the behavior.
+pub struct Plugin {
+    pub path: PathBuf,
+    pub info: &'static BehaviorInfo,
+}
+
+static PLUGINS: OnceLock<Vec<Plugin>> = OnceLock::new();
+
+/// The loaded plugins (none before `load`).
+pub fn plugins() -> &'static [Plugin] {
+    PLUGINS.get().map(Vec::as_slice).unwrap_or(&[])
+}
+
+/// Where plugins are looked for: `$TELEGRAM_ANALYZER_PLUGINS`, then
+/// `plugins/` in the config folder (see `config::config_dir`).
+pub fn search_path() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = env::var_os(PLUGIN_PATH_VAR)
+        .map(|var| env::split_paths(&var).collect())
+        .unwrap_or_default();
+    paths.extend(config::config_dir().map(|dir| dir.join("plugins")));
+    paths
+}
+
+/// Describes every executable in `paths` (each a folder of plugins or a
+/// plugin) and registers it; only the first call of a process loads anything.
+/// A plugin that can't describe itself, whose name, file stem or option names
+/// aren't plain names (see [`is_plain_name`]), whose options clash with
+/// `global` (the arguments every subcommand takes) or its own arguments, or
+/// whose name is taken, is skipped with a warning on stderr.
+pub fn load(paths: &[PathBuf], global: &[String]) -> &'static [Plugin] {
+    PLUGINS.get_or_init(|| {
+        let mut plugins: Vec<Plugin> = Vec::new();
+        for path in paths.iter().flat_map(|p| executables(p)) {
+            let taken = |name: &str, plugins: &[Plugin]| {
+                REGISTRY.iter().any(|i| i.name == name) || plugins.iter().any(|p| p.info.name == name)
+            };
+            match describe(&path, global) {
+                Ok(info) if taken(info.name, &plugins) => {
+                    eprintln!("→ Skipping plugin {}: “{}” is already taken", path.display(), info.name)
+                }
+                Ok(info) => plugins.push(Plugin { path, info }),
+                Err(e) => eprintln!("→ Skipping plugin {}: {}", path.display(), e),
+            }
+        }
+        plugins
+    })
+}
+
+/// `path` itself if it is an executable, else the executables in it, by name.
+fn executables(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return if is_executable(path) { vec![path.to_path_buf()] } else { Vec::new() };
+    }
+    let mut found: Vec<PathBuf> = fs::read_dir(path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    found.retain(|p| p.is_file() && is_executable(p));
+    found.sort();
+    found
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &Path) -> bool {
+    true
+}
+
+/// The manifest printed by `<plugin> describe`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    name: String,
+    description: String,
+    #[serde(rename = "type")]
+    behavior_type: Option<String>,
+    #[serde(default)]
+    inputs: Vec<String>,
+    #[serde(default)]
+    options: Vec<OptionManifest>,
+    file_stem: Option<String>,
+    columns: Vec<ColumnManifest>,
+    schema_version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OptionManifest {
+    name: String,
+    #[serde(default)]
+    help: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnManifest {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// What `<plugin> run` prints.
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    rows: Vec<Map<String, Value>>,
+    #[serde(default)]
+    summary: Map<String, Value>,
+    summary_line: Option<String>,
+}
+
+/// Arguments of every behavior subcommand (see `behavior_command` in main),
+/// which a plugin's options can't be named after.
+const SUBCOMMAND_ARGS: &[&str] = &["json", "input", "folder", "file", "output", "format", "help"];
+
+/// Runs `<plugin> describe` and turns its manifest into a registry entry
+/// (or says what's wrong with it).
+fn describe(path: &Path, global: &[String]) -> Result<&'static BehaviorInfo, String> {
+    let output = Command::new(path)
+        .arg("describe")
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("cannot start it: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("`describe` exited with {}", output.status));
+    }
+    info_from_manifest(&output.stdout, global)
+}
+
+/// Checks a manifest and turns it into a registry entry. Entries live as long
+/// as the process, like the built-in ones.
+fn info_from_manifest(manifest: &[u8], global: &[String]) -> Result<&'static BehaviorInfo, String> {
+    let manifest: Manifest = serde_json::from_slice(manifest).map_err(|e| format!("invalid manifest: {}", e))?;
+
+    let behavior_type = match manifest.behavior_type.as_deref() {
+        Some(name) => BehaviorType::from_str(name, true).map_err(|_| format!("unknown type “{}”", name))?,
+        None => BehaviorType::Plugin,
+    };
+    let inputs = manifest
+        .inputs
+        .iter()
+        .map(|name| {
+            [Input::Json, Input::Folder, Input::File]
+                .into_iter()
+                .find(|input| input.flag().trim_start_matches("--") == name)
+                .ok_or_else(|| format!("unknown input “{}”", name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(n) = (1..inputs.len()).find(|&n| inputs[..n].contains(&inputs[n])) {
+        return Err(format!("input “{}” is listed twice", manifest.inputs[n]));
+    }
+    let columns = manifest
+        .columns
+        .into_iter()
+        .map(|c| {
+            let kind = column_type(&c.kind).ok_or_else(|| format!("unknown column type “{}”", c.kind))?;
+            Ok(Column::new(leak(c.name), kind))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let options: Vec<BehaviorOption> = manifest
+        .options
+        .into_iter()
+        .map(|o| BehaviorOption {
+            name: leak(o.name),
+            help: leak(o.help),
+        })
+        .collect();
+
+    // Both end up in file paths and table names
+    let file_stem = manifest.file_stem.unwrap_or_else(|| manifest.name.replace('-', "_"));
+    for (what, value) in [("name", &manifest.name), ("file_stem", &file_stem)] {
+        if !is_plain_name(value) {
+            return Err(format!("{} “{}” isn't a plain name ([a-z0-9][a-z0-9_-]*)", what, value));
+        }
+    }
+    // Options become arguments of the plugin's subcommand
+    for (n, option) in options.iter().enumerate() {
+        if !is_plain_name(option.name) {
+            return Err(format!("option “{}” isn't a plain name ([a-z0-9][a-z0-9_-]*)", option.name));
+        }
+        if SUBCOMMAND_ARGS.contains(&option.name) || global.iter().any(|g| g == option.name) {
+            return Err(format!("option “{}” is already an argument of every behavior", option.name));
+        }
+        if options[..n].iter().any(|o| o.name == option.name) {
+            return Err(format!("option “{}” is listed twice", option.name));
+        }
+    }
+    Ok(Box::leak(Box::new(BehaviorInfo {
+        name: leak(manifest.name),
+        description: leak(manifest.description),
+        behavior_type,
+        inputs: Box::leak(inputs.into_boxed_slice()),
+        options: Box::leak(options.into_boxed_slice()),
+        file_stem: leak(file_stem),
+        columns: Box::leak(columns.into_boxed_slice()),
+        schema_version: manifest.schema_version.unwrap_or(1),
+        build: Some(build),
+    })))
+}
+
+/// `[a-z0-9][a-z0-9_-]*`: safe as a subcommand, a file name and part of a table name.
+fn is_plain_name(name: &str) -> bool {
+    let plain = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    name.starts_with(plain) && name.chars().all(|c| plain(c) || c == '_' || c == '-')
+}
+
+fn column_type(name: &str) -> Option<ColumnType> {
+    [
+        ColumnType::Text,
+        ColumnType::Category,
+        ColumnType::Integer,
+        ColumnType::Float,
+        ColumnType::Date,
+        ColumnType::Timestamp,
+    ]
+    .into_iter()
+    .find(|kind| kind.as_str() == name)
+}
+
+fn build(info: &'static BehaviorInfo, args: &BehaviorArgs) -> Result<Box<dyn DynBehavior>, Box<dyn Error>> {
+    let plugin = plugins()
+        .iter()
+        .find(|p| std::ptr::eq(p.info, info))
+        .expect("plugin entries are only built by `load`");
+    Ok(Box::new(PluginBehavior {
+        plugin,
+        args: args.clone(),
+    }))
+}
+
+/// PluginBehavior: runs a plugin (see `Plugin`) with the behavior's inputs
+/// and options and reads its rows back, typed by the declared columns.
+pub struct PluginBehavior {
+    pub plugin: &'static Plugin,
+    pub args: BehaviorArgs,
+}
+
+/// Writes the `run` request to a plugin's stdin, a message at a time.
+fn write_request(stdin: ChildStdin, header: &Value, messages: Option<&[Message]>) -> Result<(), Box<dyn Error>> {
+    let mut out = BufWriter::new(stdin);
+    serde_json::to_writer(&mut out, header)?;
+    out.write_all(b"\n")?;
+    if let Some(messages) = messages {
+        for chunk in messages.chunks(1024) {
+            dump::message_report(chunk).render(&OutputFormat::Jsonl, &mut out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// A plugin's result, its rows already typed.
+#[derive(Debug, Clone)]
+pub struct PluginResult {
+    pub behavior: &'static str,
+    pub file_stem: &'static str,
+    pub schema_version: u32,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Cell>>,
+    pub summary: Vec<(&'static str, Cell)>,
+    pub summary_line: String,
+}
+
+impl Behavior for PluginBehavior {
+    type Output = PluginResult;
+
+    fn run(&self, ctx: &Context) -> Result<PluginResult, Box<dyn Error>> {
+        let info = self.plugin.info;
+        let fail = |message: String| AnalyzerError::Plugin {
+            path: self.plugin.path.clone(),
+            message,
+        };
+
+        // 1) The request: a header line, then the messages as JSON Lines
+        let messages = if info.inputs.contains(&Input::Json) {
+            Some(ctx.messages(&self.args.json)?)
+        } else {
+            None
+        };
+        let header = json!({
+            "behavior": info.name,
+            "options": self.args.options,
+            "folder": self.args.folder,
+            "file": self.args.file,
+            "timezone": ctx.timezone.name(),
+            "messages": messages.as_ref().map_or(0, |m| m.len()),
+        });
+
+        // 2) Run it, streaming the request from a thread so neither side blocks on a full pipe
+        let mut child = Command::new(&self.plugin.path)
+            .arg("run")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| fail(format!("cannot start it: {}", e)))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let output = thread::scope(|scope| {
+            // A plugin may stop reading once it has what it needs, so a broken pipe isn't an error
+            scope.spawn(|| {
+                let _ = write_request(stdin, &header, messages.as_deref().map(Vec::as_slice));
+            });
+            child.wait_with_output()
+        })
+        .map_err(|e| fail(e.to_string()))?;
+        if !output.status.success() {
+            return Err(fail(format!("exited with {}", output.status)).into());
+        }
+
+        // 3) The response, typed by the declared columns
+        let response: Response =
+            serde_json::from_slice(&output.stdout).map_err(|e| fail(format!("invalid response: {}", e)))?;
+        let mut rows = Vec::with_capacity(response.rows.len());
+        for (n, row) in response.rows.iter().enumerate() {
+            let cells = info.columns.iter().map(|column| {
+                let value = row.get(column.name).unwrap_or(&Value::Null);
+                cell(column.kind, value).ok_or_else(|| {
+                    fail(format!(
+                        "row {}: column “{}” expects {}, got {}",
+                        n + 1,
+                        column.name,
+                        column.kind.as_str(),
+                        value
+                    ))
+                })
+            });
+            rows.push(cells.collect::<Result<Vec<_>, _>>()?);
+        }
+        let summary = response
+            .summary
+            .into_iter()
+            .map(|(key, value)| (leak(key), summary_cell(value)))
+            .collect();
+        Ok(PluginResult {
+            behavior: info.name,
+            file_stem: info.file_stem,
+            schema_version: info.schema_version,
+            columns: info.columns.to_vec(),
+            summary_line: response
+                .summary_line
+                .unwrap_or_else(|| format!("→ {}: {} rows", info.name, rows.len())),
+            rows,
+            summary,
+        })
+    }
+}
+
+/// A row value as a cell of a `kind` column, if it is one.
+fn cell(kind: ColumnType, value: &Value) -> Option<Cell> {
+    Some(match (kind, value) {
+        (_, Value::Null) => Cell::Null,
+        (ColumnType::Integer, v) => Cell::Int(v.as_i64()?),
+        (ColumnType::Float, v) => Cell::Float(v.as_f64()?),
+        (ColumnType::Date, Value::String(s)) => Cell::Date(NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?),
+        (ColumnType::Timestamp, Value::String(s)) => Cell::Time(parse_iso_timestamp(s)?),
+        (ColumnType::Text | ColumnType::Category, Value::String(s)) => Cell::Text(s.clone()),
+        (ColumnType::Text | ColumnType::Category, v) => Cell::Text(v.to_string()),
+        _ => return None,
+    })
+}
+
+/// Summary values aren't declared, so they keep their JSON type. (Their keys
+/// are leaked like the registry entries: a run reads a handful.)
+fn summary_cell(value: Value) -> Cell {
+    match value {
+        Value::Null => Cell::Null,
+        Value::String(s) => Cell::Text(s),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Cell::Int(i),
+            None => Cell::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        other => Cell::Text(other.to_string()),
+    }
+}
+
+impl From<&PluginResult> for Report {
+    fn from(result: &PluginResult) -> Self {
+        Report {
+            behavior: result.behavior,
+            file_stem: result.file_stem,
+            schema_version: result.schema_version,
+            columns: result.columns.clone(),
+            rows: result.rows.clone(),
+            summary: result.summary.clone(),
+            summary_line: result.summary_line.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(inputs: &str, options: &str) -> Vec<u8> {
+        format!(
+            r#"{{ "name": "scam-score", "description": "Scores scams", "inputs": {}, "options": {},
+                 "columns": [{{ "name": "score", "type": "integer" }}] }}"#,
+            inputs, options
+        )
+        .into_bytes()
+    }
+
+    fn global() -> Vec<String> {
+        ["timezone", "since", "pseudonym_key", "pseudonym-key"].map(String::from).to_vec()
+    }
+
+    fn rejection(manifest: &[u8]) -> String {
+        match info_from_manifest(manifest, &global()) {
+            Ok(info) => panic!("“{}” was accepted", info.name),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_manifest() {
+        let info = info_from_manifest(
+            &manifest(r#"["json", "folder"]"#, r#"[{ "name": "min-score", "help": "Lowest score" }]"#),
+            &global(),
+        )
+        .unwrap();
+        assert_eq!(info.name, "scam-score");
+        assert_eq!(info.file_stem, "scam_score");
+        assert_eq!(info.inputs, [Input::Json, Input::Folder]);
+        assert_eq!(info.options[0].name, "min-score");
+        assert_eq!(info.schema_version, 1);
+    }
+
+    #[test]
+    fn rejects_options_that_clash_with_other_arguments() {
+        for name in ["output", "format", "json", "input", "folder", "file", "help", "timezone", "pseudonym-key"] {
+            let options = format!(r#"[{{ "name": "{}" }}]"#, name);
+            let e = rejection(&manifest(r#"["json"]"#, &options));
+            assert!(e.contains("already an argument"), "{}: {}", name, e);
+        }
+    }
+
+    #[test]
+    fn rejects_repeated_or_odd_option_names() {
+        let e = rejection(&manifest("[]", r#"[{ "name": "limit" }, { "name": "limit" }]"#));
+        assert!(e.contains("“limit” is listed twice"), "{}", e);
+        for name in ["", "Limit", "-limit", "max limit"] {
+            let options = format!(r#"[{{ "name": "{}" }}]"#, name);
+            let e = rejection(&manifest("[]", &options));
+            assert!(e.contains("isn't a plain name"), "{}: {}", name, e);
+        }
+    }
+
+    #[test]
+    fn rejects_repeated_inputs() {
+        let e = rejection(&manifest(r#"["folder", "json", "folder"]"#, "[]"));
+        assert!(e.contains("“folder” is listed twice"), "{}", e);
+    }
+}
@@ -0,0 +1,216 @@
+// src/progress.rs
+
+use crate::report::{Column, ColumnType, Report};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Whether bars are drawn (see [`enable`]).
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Turns the progress bars on (or off). They are drawn on stderr; the CLI
+/// turns them on when stdout is a terminal, so piped output stays clean.
+pub fn enable(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+/// A bar over the `total` bytes of `label`, e.g. reading an export (see `ProgressBar::wrap_read`).
+pub fn bytes(label: &str, total: u64) -> ProgressBar {
+    bar(label, Some(total), "{prefix} [{bar:30}] {bytes}/{total_bytes} ({eta})")
+}
+
+/// A bar over `total` items of `label`, e.g. messages parsed or behaviors run.
+pub fn count(label: &str, total: u64) -> ProgressBar {
+    bar(label, Some(total), "{prefix} [{bar:30}] {pos}/{len} {msg}")
+}
+
+/// A spinner for work of unknown size, e.g. walking a folder.
+pub fn spinner(label: &str) -> ProgressBar {
+    bar(label, None, "{prefix} {spinner} {msg} ({elapsed})")
+}
+
+fn bar(label: &str, total: Option<u64>, template: &str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let bars = BARS.get_or_init(|| MultiProgress::with_draw_target(ProgressDrawTarget::stderr()));
+    let style = ProgressStyle::with_template(template).expect("valid progress template");
+    let bar = match total {
+        Some(total) => ProgressBar::new(total),
+        None => ProgressBar::new_spinner(),
+    };
+    let bar = bars.add(bar.with_style(style.progress_chars("=> ")).with_prefix(label.to_string()));
+    bar.enable_steady_tick(std::time::Duration::from_millis(100));
+    bar
+}
+
+/// Whether [`PeakAlloc`] counts (see [`enable_timings`]).
+static TRACKING: AtomicBool = AtomicBool::new(false);
+
+/// Heap in use, and the most in use since the current phase began. Blocks
+/// allocated before tracking began are freed uncounted, so `LIVE` may dip
+/// below zero.
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+
+/// The system allocator, counting the heap in use so [`phase`] can report
+/// its peak. The CLI installs it with `#[global_allocator]`. Until
+/// [`enable_timings`] it only checks a flag, so runs without timings don't
+/// share a counter across threads on every allocation.
+pub struct PeakAlloc;
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() && TRACKING.load(Ordering::Relaxed) {
+            grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() && TRACKING.load(Ordering::Relaxed) {
+            grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        if TRACKING.load(Ordering::Relaxed) {
+            LIVE.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() && TRACKING.load(Ordering::Relaxed) {
+            LIVE.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+            grew(new_size);
+        }
+        new
+    }
+}
+
+fn grew(size: usize) {
+    let live = LIVE.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+    if live > PEAK.load(Ordering::Relaxed) {
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+/// One timed step of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Phase {
+    pub name: String,
+    pub seconds: f64,
+    /// The most heap in use during the phase; `None` without [`PeakAlloc`],
+    /// or for a phase that ran alongside others (see [`concurrent_phase`]).
+    pub peak_bytes: Option<usize>,
+}
+
+/// The phases recorded so far, in the order they began; `None` until timings are enabled.
+static PHASES: Mutex<Option<Vec<Phase>>> = Mutex::new(None);
+
+/// Starts recording phases, and counting the heap for their peaks.
+pub fn enable_timings() {
+    PHASES.lock().unwrap().get_or_insert_with(Vec::new);
+    TRACKING.store(true, Ordering::Relaxed);
+}
+
+/// The phases recorded so far (`None` unless timings are enabled).
+pub fn timings() -> Option<Vec<Phase>> {
+    PHASES.lock().unwrap().clone()
+}
+
+/// Runs `f` as the phase `name`. Phases may nest (a parse inside a
+/// behavior's run): the outer one's peak includes the inner one's. On a
+/// rayon worker, where other phases may run alongside, it is a
+/// [`concurrent_phase`] instead: one peak is shared by the whole process.
+pub fn phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    if rayon::current_thread_index().is_some() {
+        return concurrent_phase(name, f);
+    }
+    let Some(slot) = begin(name) else {
+        return f();
+    };
+    let outer_peak = PEAK.swap(LIVE.load(Ordering::Relaxed), Ordering::Relaxed);
+    let start = Instant::now();
+    let result = f();
+    let peak = PEAK.fetch_max(outer_peak, Ordering::Relaxed);
+    end(slot, start, (peak > 0).then_some(peak as usize));
+    result
+}
+
+/// Runs `f` as the phase `name`, timing only its wall time: for phases that
+/// run side by side, whose peaks can't be told apart.
+pub fn concurrent_phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let Some(slot) = begin(name) else {
+        return f();
+    };
+    let start = Instant::now();
+    let result = f();
+    end(slot, start, None);
+    result
+}
+
+fn begin(name: &str) -> Option<usize> {
+    let mut phases = PHASES.lock().unwrap();
+    let phases = phases.as_mut()?;
+    phases.push(Phase {
+        name: name.to_string(),
+        seconds: 0.0,
+        peak_bytes: None,
+    });
+    Some(phases.len() - 1)
+}
+
+fn end(slot: usize, start: Instant, peak_bytes: Option<usize>) {
+    if let Some(phase) = PHASES.lock().unwrap().as_mut().and_then(|p| p.get_mut(slot)) {
+        phase.seconds = start.elapsed().as_secs_f64();
+        phase.peak_bytes = peak_bytes;
+    }
+}
+
+/// Version of the `timings` report.
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const COLUMNS: &[Column] = &[
+    Column::new("phase", ColumnType::Text),
+    Column::new("seconds", ColumnType::Float),
+    Column::new("peak_mb", ColumnType::Float),
+];
+
+/// Megabytes, to one decimal.
+fn megabytes(bytes: usize) -> f64 {
+    (bytes as f64 / 1e5).round() / 10.0
+}
+
+impl From<&[Phase]> for Report {
+    fn from(phases: &[Phase]) -> Self {
+        let peak = phases.iter().filter_map(|p| p.peak_bytes).max();
+        Report {
+            behavior: "timings",
+            file_stem: "timings",
+            schema_version: SCHEMA_VERSION,
+            columns: COLUMNS.to_vec(),
+            rows: phases
+                .iter()
+                .map(|p| {
+                    let seconds = (p.seconds * 1000.0).round() / 1000.0;
+                    vec![p.name.as_str().into(), seconds.into(), p.peak_bytes.map(megabytes).into()]
+                })
+                .collect(),
+            summary: vec![("phases", phases.len().into()), ("peak_mb", peak.map(megabytes).into())],
+            summary_line: match peak {
+                Some(peak) => format!("→ Timings: {} phases, peak memory {} MB", phases.len(), megabytes(peak)),
+                None => format!("→ Timings: {} phases", phases.len()),
+            },
+        }
+    }
+}
@@ -0,0 +1,299 @@
+// src/pseudonym.rs
+
+use crate::model::{url_regex, Chat, Message, TextEntity};
+use crate::pii::{email_regex, is_phone, phone_regex};
+use hmac::{Hmac, Mac};
+use regex::{Captures, Regex};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+/// Where `--pseudonymize` reads its key when `--pseudonym-key` isn't given.
+pub const KEY_VAR: &str = "TELEGRAM_ANALYZER_PSEUDONYM_KEY";
+
+/// Chat types named after the other party, whose name and id are pseudonymized too.
+const PERSONAL_CHATS: &[&str] = &["personal_chat", "bot_chat"];
+
+/// Replaces identities with stable pseudonyms: HMAC-SHA256 of the identity
+/// under a user-supplied key, so the same key maps the same person to the
+/// same pseudonym in every behavior, format and run, and cross-references
+/// (a sender in one table, a forwarder in another) stay valid.
+///
+///   - `from_id`: `user123` → `user3fa2b9c01d2e` (the kind prefix stays),
+///   - names (`from`, `forwarded_from`, personal chats): `Person 3fa2b9c01d2e`,
+///   - usernames (`via_bot`, mentions): `@u3fa2b9c01d2e`.
+///
+/// Names and usernames ending in “bot” keep that ending (`3fa2b9c01d2e_bot`),
+/// so bots are still told apart. With `scrub`, phone numbers and emails in
+/// message text and entities become `[phone]` and `[email]`, and mentions
+/// (`@username` and `mention_name`) pseudonyms; URLs are left as they are,
+/// except links to a user (`t.me/<username>`, `tg://user?id=`), `mailto:`
+/// and `tel:`.
+pub struct Pseudonymizer {
+    key: Vec<u8>,
+    pub scrub: bool,
+}
+
+impl Pseudonymizer {
+    pub fn new(key: &[u8], scrub: bool) -> Self {
+        Self {
+            key: key.to_vec(),
+            scrub,
+        }
+    }
+
+    /// The first 48 bits of the keyed hash of `value` in `namespace`, as hex.
+    fn digest(&self, namespace: &str, value: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        mac.update(namespace.as_bytes());
+        mac.update(&[0]);
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes()[..6].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// A sender id, e.g. `user123` or `channel456`.
+    pub fn id(&self, id: &str) -> String {
+        let kind = id.trim_end_matches(|c: char| c.is_ascii_digit());
+        format!("{}{}", kind, self.digest("id", id))
+    }
+
+    /// A display name.
+    pub fn name(&self, name: &str) -> String {
+        let digest = self.digest("name", name);
+        if is_bot_name(name) {
+            format!("{}_bot", digest)
+        } else {
+            format!("Person {}", digest)
+        }
+    }
+
+    /// A username, with or without its `@` (usernames are case-insensitive).
+    pub fn username(&self, username: &str) -> String {
+        let username = username.trim_start_matches('@');
+        let digest = self.digest("username", &username.to_lowercase());
+        if is_bot_name(username) {
+            format!("@{}_bot", digest)
+        } else {
+            format!("@u{}", digest)
+        }
+    }
+
+    /// `text` with phone numbers and emails replaced and mentions
+    /// pseudonymized; `apply` only does this with `scrub`.
+    pub fn scrub_text(&self, text: &str) -> String {
+        // URLs can hold digits and `@`s, so only the text between them is scrubbed
+        let mut scrubbed = String::with_capacity(text.len());
+        let mut last = 0;
+        for url in url_regex().find_iter(text) {
+            scrubbed.push_str(&self.scrub_plain(&text[last..url.start()]));
+            scrubbed.push_str(&self.scrub_href(url.as_str()));
+            last = url.end();
+        }
+        scrubbed.push_str(&self.scrub_plain(&text[last..]));
+        scrubbed
+    }
+
+    fn scrub_plain(&self, text: &str) -> String {
+        let text = email_regex().replace_all(text, "[email]");
+        let text = mention_regex().replace_all(&text, |c: &Captures| format!("{}{}", &c[1], self.username(&c[2])));
+        phone_regex()
+            .replace_all(&text, |c: &Captures| {
+                if is_phone(&c[0]) {
+                    "[phone]".to_string()
+                } else {
+                    c[0].to_string()
+                }
+            })
+            .into_owned()
+    }
+
+    /// A link target: `mailto:` and `tel:` lose the address, and links to a
+    /// user point at the pseudonym (`tg://user?id=` at the number of the
+    /// same hash as `from_id`); other URLs stay.
+    fn scrub_href(&self, href: &str) -> String {
+        if href.starts_with("mailto:") {
+            return "mailto:[email]".to_string();
+        }
+        if href.starts_with("tel:") {
+            return "tel:[phone]".to_string();
+        }
+        if let Some(id) = href.strip_prefix("tg://user?id=") {
+            return format!("tg://user?id={}", self.number("id", &format!("user{}", id)));
+        }
+        user_link_regex()
+            .replace(href, |c: &Captures| {
+                if RESERVED_LINKS.contains(&c[2].to_lowercase().as_str()) {
+                    c[0].to_string()
+                } else {
+                    format!("{}{}", &c[1], self.username(&c[2]).trim_start_matches('@'))
+                }
+            })
+            .into_owned()
+    }
+
+    /// `entity` with its text and link target scrubbed.
+    fn scrub_entity(&self, entity: &TextEntity) -> TextEntity {
+        let text = match entity.kind.as_str() {
+            // A link's text is its URL
+            "link" => self.scrub_href(&entity.text),
+            "mention" => self.username(&entity.text),
+            "mention_name" => self.name(&entity.text),
+            "phone" => "[phone]".to_string(),
+            "email" => "[email]".to_string(),
+            _ => self.scrub_text(&entity.text),
+        };
+        TextEntity {
+            kind: entity.kind.clone(),
+            text,
+            href: entity.href.as_deref().map(|href| self.scrub_href(href)),
+        }
+    }
+
+    /// Scrubs the entities of `msg` and its text, where each entity's span
+    /// gets the entity's scrubbed text (so a `mention_name` is renamed in the
+    /// text too, which the plain scrub can't recognize).
+    fn scrub_message(&self, msg: &mut Message) {
+        let mut text = String::with_capacity(msg.text.len());
+        let mut rest = msg.text.as_str();
+        for entity in &mut msg.entities {
+            let scrubbed = self.scrub_entity(entity);
+            // Entities come in text order; one not found in the rest is left to the plain scrub
+            if let Some(at) = rest.find(entity.text.as_str()).filter(|_| !entity.text.is_empty()) {
+                text.push_str(&self.scrub_text(&rest[..at]));
+                text.push_str(&scrubbed.text);
+                rest = &rest[at + entity.text.len()..];
+            }
+            *entity = scrubbed;
+        }
+        text.push_str(&self.scrub_text(rest));
+        msg.text = text;
+    }
+
+    /// Pseudonymizes `messages` in place (and scrubs their text with `scrub`).
+    pub fn apply(&self, messages: &mut [Message]) {
+        let mut chats: HashMap<*const Chat, Arc<Chat>> = HashMap::new();
+        for msg in messages {
+            msg.from_id = msg.from_id.as_deref().map(|id| self.id(id));
+            msg.from = msg.from.as_deref().map(|name| self.name(name));
+            msg.forwarded_from = msg.forwarded_from.as_deref().map(|name| self.name(name));
+            msg.via_bot = msg.via_bot.as_deref().map(|bot| self.username(bot));
+            if msg.chat.kind.as_deref().is_some_and(|kind| PERSONAL_CHATS.contains(&kind)) {
+                let chat = chats.entry(Arc::as_ptr(&msg.chat)).or_insert_with(|| {
+                    Arc::new(Chat {
+                        id: msg.chat.id.map(|id| self.number("chat", &id.to_string())),
+                        name: msg.chat.name.as_deref().map(|name| self.name(name)),
+                        kind: msg.chat.kind.clone(),
+                    })
+                });
+                msg.chat = Arc::clone(chat);
+            }
+            if self.scrub {
+                self.scrub_message(msg);
+            }
+        }
+    }
+
+    /// A positive number from the keyed hash, for numeric ids.
+    fn number(&self, namespace: &str, value: &str) -> i64 {
+        i64::from_str_radix(&self.digest(namespace, value), 16).expect("48 bits of hex")
+    }
+}
+
+/// Bot usernames end in “bot”, and so do most bot names.
+fn is_bot_name(name: &str) -> bool {
+    name.to_lowercase().ends_with("bot")
+}
+
+/// `t.me` paths that aren't usernames.
+const RESERVED_LINKS: &[&str] = &[
+    "addemoji", "addlist", "addstickers", "addtheme", "boost", "joinchat", "proxy", "setlanguage", "share", "socks",
+];
+
+/// A link to a user or channel, `t.me/<username>`; group 1 is what comes
+/// before the username, group 2 the username.
+fn user_link_regex() -> &'static Regex {
+    static USER_LINK: OnceLock<Regex> = OnceLock::new();
+    USER_LINK.get_or_init(|| {
+        Regex::new(r"^((?:https?://)?(?:www\.)?(?:t|telegram)\.me/)([A-Za-z][A-Za-z0-9_]{3,31})\b").unwrap()
+    })
+}
+
+/// `@username` after a non-word character; group 1 is that character, group 2 the username.
+pub fn mention_regex() -> &'static Regex {
+    static MENTION: OnceLock<Regex> = OnceLock::new();
+    MENTION.get_or_init(|| Regex::new(r"(^|[^\w@])@([A-Za-z][A-Za-z0-9_]{3,31})\b").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(value: serde_json::Value) -> Message {
+        let chat = Arc::new(Chat {
+            id: Some(1),
+            name: Some("Group".to_string()),
+            kind: Some("private_supergroup".to_string()),
+        });
+        Message::from_json(&value, chat).unwrap()
+    }
+
+    #[test]
+    fn same_key_and_input_give_the_same_pseudonym() {
+        let (a, b) = (Pseudonymizer::new(b"secret", false), Pseudonymizer::new(b"secret", true));
+        assert_eq!(a.id("user123"), b.id("user123"));
+        assert_eq!(a.name("Ann Lee"), b.name("Ann Lee"));
+        assert_eq!(a.username("@AnnLee"), b.username("annlee"));
+        assert!(a.id("user123").starts_with("user"));
+        assert_ne!(a.id("user123"), a.id("user124"));
+
+        let other = Pseudonymizer::new(b"other", false);
+        assert_ne!(a.id("user123"), other.id("user123"));
+        assert_ne!(a.name("Ann Lee"), other.name("Ann Lee"));
+    }
+
+    #[test]
+    fn scrub_covers_entity_text_and_links() {
+        let p = Pseudonymizer::new(b"secret", true);
+        let mut messages = vec![message(json!({
+            "id": 1,
+            "type": "message",
+            "date": "2024-01-31T10:00:00",
+            "from": "Ann Lee",
+            "from_id": "user1",
+            "text": [
+                "Ask ",
+                { "type": "mention_name", "text": "Bob Stone", "user_id": 42 },
+                " or ",
+                { "type": "text_link", "text": "his profile", "href": "tg://user?id=42" },
+                ", call ",
+                { "type": "phone", "text": "+44 20 7946 0958" },
+                ", see ",
+                { "type": "text_link", "text": "channel", "href": "https://t.me/bobstone/12" },
+                " or ",
+                { "type": "link", "text": "https://example.com/a" },
+            ],
+        }))];
+        p.apply(&mut messages);
+        let msg = &messages[0];
+
+        for secret in ["Bob Stone", "7946", "bobstone", "id=42"] {
+            assert!(!msg.text.contains(secret), "{} in {}", secret, msg.text);
+            for e in &msg.entities {
+                assert!(!e.text.contains(secret), "{} in {:?}", secret, e);
+                assert!(!e.href.as_deref().unwrap_or_default().contains(secret), "{} in {:?}", secret, e);
+            }
+        }
+        let bob = p.name("Bob Stone");
+        assert_eq!(msg.entities[0].text, bob);
+        assert!(msg.text.starts_with(&format!("Ask {} or his profile, call [phone]", bob)));
+        let user_link = format!("tg://user?id={}", p.number("id", "user42"));
+        assert_eq!(msg.entities[1].href.as_deref(), Some(user_link.as_str()));
+        assert_eq!(
+            msg.entities[3].href.as_deref(),
+            Some(format!("https://t.me/{}/12", p.username("bobstone").trim_start_matches('@')).as_str())
+        );
+        assert_eq!(msg.entities[4].text, "https://example.com/a");
+        assert!(msg.text.ends_with(" or https://example.com/a"));
+    }
+}
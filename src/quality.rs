@@ -0,0 +1,246 @@
+// src/quality.rs
+
+use crate::model::{parse_iso_timestamp, Message, MessageKind};
+use crate::report::{Column, ColumnType, Report};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// How the loader treats a malformed message (see [`problem`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Keep malformed messages and tally them in a [`DataQuality`] report (the default).
+    /// Behaviors skip only those missing what they need: undated messages don't
+    /// count in time slots, days or charts.
+    #[default]
+    Lenient,
+    /// Fail the load on the first malformed message.
+    Strict,
+}
+
+/// What's wrong with a malformed message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Problem {
+    /// Neither `date` nor `date_unixtime`.
+    MissingDate,
+    /// A `date` (or `date_unixtime`) that isn't a timestamp.
+    BadDate,
+    /// A `type` other than `message` or `service`.
+    UnknownType,
+    /// A `text` that isn't text, or only the start of its `text_entities`.
+    TruncatedText,
+}
+
+impl Problem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Problem::MissingDate => "missing-date",
+            Problem::BadDate => "bad-date",
+            Problem::UnknownType => "unknown-type",
+            Problem::TruncatedText => "truncated-text",
+        }
+    }
+
+    /// Human-readable, for error messages.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Problem::MissingDate => "has no date",
+            Problem::BadDate => "has a date that isn't a timestamp",
+            Problem::UnknownType => "has an unknown type",
+            Problem::TruncatedText => "has truncated or unreadable text",
+        }
+    }
+}
+
+/// Sample message ids kept per reason.
+pub const SAMPLES: usize = 5;
+
+/// Version of the `data_quality` report.
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const COLUMNS: &[Column] = &[
+    Column::new("reason", ColumnType::Category),
+    Column::new("count", ColumnType::Integer),
+    Column::new("sample_ids", ColumnType::Text),
+];
+
+/// The malformed messages a lenient load kept, grouped by problem.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DataQuality {
+    /// Messages read, malformed ones included.
+    pub messages: usize,
+    pub malformed: BTreeMap<Problem, Malformed>,
+}
+
+/// The messages with one problem.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Malformed {
+    pub count: usize,
+    /// The first few ids, in load order.
+    pub sample_ids: Vec<i64>,
+}
+
+impl DataQuality {
+    pub fn flag(&mut self, reason: Problem, message_id: i64) {
+        let group = self.malformed.entry(reason).or_default();
+        group.count += 1;
+        if group.sample_ids.len() < SAMPLES {
+            group.sample_ids.push(message_id);
+        }
+    }
+
+    pub fn malformed_total(&self) -> usize {
+        self.malformed.values().map(|s| s.count).sum()
+    }
+
+    /// Adds the tally of another load.
+    pub fn merge(&mut self, other: &DataQuality) {
+        self.messages += other.messages;
+        for (reason, theirs) in &other.malformed {
+            let ours = self.malformed.entry(*reason).or_default();
+            ours.count += theirs.count;
+            let room = SAMPLES.saturating_sub(ours.sample_ids.len());
+            ours.sample_ids.extend(theirs.sample_ids.iter().take(room));
+        }
+    }
+}
+
+/// What's wrong with `msg`, normalized from `value`, if anything. Checked in
+/// this order, so a message is tallied under its first problem only.
+pub fn problem(value: &Value, msg: &Message) -> Option<Problem> {
+    let present = |key: &str| value.get(key).is_some_and(|v| !v.is_null());
+    if msg.date.is_none() {
+        return Some(if present("date") || present("date_unixtime") {
+            Problem::BadDate
+        } else {
+            Problem::MissingDate
+        });
+    }
+    // A `date` next to a valid `date_unixtime` is ignored by the model, but still has to be one
+    if let Some(date) = value.get("date").filter(|d| !d.is_null()) {
+        if date.as_str().and_then(parse_iso_timestamp).is_none() {
+            return Some(Problem::BadDate);
+        }
+    }
+    if matches!(msg.kind, MessageKind::Other(_)) {
+        return Some(Problem::UnknownType);
+    }
+    if truncated(value, msg) {
+        return Some(Problem::TruncatedText);
+    }
+    None
+}
+
+/// `text` is a string or an array of strings and `{ "type", "text" }` runs,
+/// and isn't cut short of its `text_entities`. Exports often differ in
+/// whitespace or in how entities split the text, so only a `text` that is a
+/// strict prefix of the entities' text counts as cut short, and an empty
+/// `text` (e.g. a sticker's with a caption entity) never does.
+fn truncated(value: &Value, msg: &Message) -> bool {
+    let runs_ok = |parts: &[Value]| {
+        parts
+            .iter()
+            .all(|p| p.is_string() || p.get("text").is_some_and(Value::is_string))
+    };
+    let text_ok = match value.get("text") {
+        None | Some(Value::Null) | Some(Value::String(_)) => true,
+        Some(Value::Array(parts)) => runs_ok(parts),
+        Some(_) => false,
+    };
+    let entities_ok = match value.get("text_entities") {
+        None | Some(Value::Null) => true,
+        Some(Value::Array(entities)) => {
+            let full: String = entities.iter().filter_map(|e| e.get("text").and_then(Value::as_str)).collect();
+            let (full, text) = (full.trim_end(), msg.text.trim_end());
+            runs_ok(entities) && (text.is_empty() || !(full.len() > text.len() && full.starts_with(text)))
+        }
+        Some(_) => false,
+    };
+    !(text_ok && entities_ok)
+}
+
+impl From<&DataQuality> for Report {
+    fn from(result: &DataQuality) -> Self {
+        let malformed = result.malformed_total();
+        let mut details: Vec<String> = result
+            .malformed
+            .iter()
+            .map(|(reason, s)| format!("{}: {}", reason.as_str(), s.count))
+            .collect();
+        if details.is_empty() {
+            details.push("none".to_string());
+        }
+        Report {
+            behavior: "data-quality",
+            file_stem: "data_quality",
+            schema_version: SCHEMA_VERSION,
+            columns: COLUMNS.to_vec(),
+            rows: result
+                .malformed
+                .iter()
+                .map(|(reason, s)| {
+                    let ids: Vec<String> = s.sample_ids.iter().map(i64::to_string).collect();
+                    vec![reason.as_str().into(), s.count.into(), ids.join(", ").into()]
+                })
+                .collect(),
+            summary: vec![("messages", result.messages.into()), ("malformed", malformed.into())],
+            summary_line: format!(
+                "→ DataQuality: {} of {} messages malformed ({}); kept, but left out where a date is needed",
+                malformed,
+                result.messages,
+                details.join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Chat;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn check(value: Value) -> Option<Problem> {
+        let msg = Message::from_json(&value, Arc::new(Chat::default())).expect("the message has an id");
+        problem(&value, &msg)
+    }
+
+    fn with_text(text: Value, entities: Value) -> Value {
+        json!({ "id": 1, "type": "message", "date": "2024-01-31T12:00:00", "text": text, "text_entities": entities })
+    }
+
+    #[test]
+    fn flags_text_cut_short_of_its_entities() {
+        let entities = json!([{ "type": "plain", "text": "Meet at " }, { "type": "bold", "text": "noon" }]);
+        assert_eq!(check(with_text(json!("Meet at"), entities)), Some(Problem::TruncatedText));
+        assert_eq!(check(with_text(json!(42), json!([]))), Some(Problem::TruncatedText));
+    }
+
+    #[test]
+    fn keeps_text_that_matches_its_entities() {
+        let entities = json!([{ "type": "plain", "text": "Meet at " }, { "type": "bold", "text": "noon" }]);
+        let runs = json!(["Meet at ", { "type": "bold", "text": "noon" }]);
+        assert_eq!(check(with_text(runs, entities.clone())), None);
+        // Trailing whitespace and line endings differ between exports
+        assert_eq!(check(with_text(json!("Meet at noon \r\n"), entities)), None);
+        assert_eq!(check(with_text(json!("Meet at noon"), Value::Null)), None);
+    }
+
+    #[test]
+    fn empty_text_is_never_cut_short() {
+        let entities = json!([{ "type": "hashtag", "text": "#sticker" }]);
+        assert_eq!(check(with_text(json!(""), entities)), None);
+        assert_eq!(check(with_text(json!(""), json!([]))), None);
+    }
+
+    #[test]
+    fn tells_missing_dates_from_bad_ones() {
+        assert_eq!(check(json!({ "id": 1, "type": "message", "text": "" })), Some(Problem::MissingDate));
+        let bad = json!({ "id": 1, "type": "message", "date": "yesterday", "text": "" });
+        assert_eq!(check(bad), Some(Problem::BadDate));
+        let unknown = json!({ "id": 1, "type": "poll", "date": "2024-01-31T12:00:00", "text": "" });
+        assert_eq!(check(unknown), Some(Problem::UnknownType));
+    }
+}
@@ -0,0 +1,748 @@
+// src/query.rs
+
+use crate::filter::{is_sender, start_of_day};
+use crate::model::Message;
+use chrono::{DateTime, Days, NaiveDate, TimeDelta, Utc};
+use chrono_tz::Tz;
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+
+/// Filter expression language, e.g.
+///
+/// ```text
+/// from:user123 AND date>=2024-01-01 AND (has:link OR text~"crypto") AND NOT forwarded
+/// ```
+///
+///   - Terms are `field<op>value` or bare flags; adjacent terms are ANDed.
+///   - `AND`, `OR`, `NOT` (any case) and parentheses combine terms;
+///     `NOT` binds tightest, then `AND`, then `OR`.
+///   - Values are bare words or double-quoted strings (`\"` and `\\` escapes).
+///
+/// Fields:
+///
+///   - `from:<id or name>`, `chat:<id or name>`, `forwarded_from:<name>`
+///   - `date<op><YYYY-MM-DD or RFC 3339>` with `:`/`=`, `!=`, `<`, `<=`, `>`, `>=`;
+///     a plain date stands for the whole day (in the run's timezone)
+///   - `id<op><n>`, `reply:<id>`
+///   - `type:<message|service>`, `media:<media_type>`
+///   - `has:<link|media|photo|file|reply|text>`
+///   - `text:<literal>` (case-insensitive substring), `text~<regex>`
+///   - flags: `forwarded`, `service`, `bot`, `via_bot` (posted through an inline bot),
+///     `edited`, `reply`
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Pred(Predicate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// What `has:` can test for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HasKind {
+    Link,
+    Media,
+    Photo,
+    File,
+    Reply,
+    Text,
+}
+
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    From(String),
+    Chat(String),
+    ForwardedFrom(String),
+    /// `[start, end)` is the compared period: a whole day for plain dates
+    /// (`day`, UTC's until placed by `Expr::in_timezone`), one second for
+    /// full timestamps.
+    Date {
+        op: CmpOp,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        day: Option<NaiveDate>,
+    },
+    Id(CmpOp, i64),
+    ReplyTo(i64),
+    Type(String),
+    Media(String),
+    Has(HasKind),
+    TextContains(String),
+    TextRegex(Regex),
+    Forwarded,
+    Service,
+    Bot,
+    ViaBot,
+    Edited,
+    Reply,
+}
+
+/// A parse error with the byte span of the offending part of the input.
+#[derive(Debug, Clone)]
+pub struct QueryError {
+    pub message: String,
+    pub input: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for QueryError {
+    /// Renders the message, the expression and a caret line under the bad part.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pad = self.input[..self.start].chars().count();
+        let width = self.input[self.start..self.end].chars().count().max(1);
+        write!(
+            f,
+            "{}\n  {}\n  {}{}",
+            self.message,
+            self.input,
+            " ".repeat(pad),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl Expr {
+    /// Parses a filter expression.
+    pub fn parse(input: &str) -> Result<Expr, QueryError> {
+        let tokens = lex(input)?;
+        let mut parser = Parser {
+            input,
+            tokens,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err(parser.error("Empty filter expression", 0, input.len()));
+        }
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            let message = if token.kind == TokenKind::RParen {
+                "Unmatched closing parenthesis"
+            } else {
+                "Unexpected input after the end of the expression"
+            };
+            return Err(parser.error(message, token.start, token.end));
+        }
+        Ok(expr)
+    }
+
+    /// Makes plain dates days of `timezone` (see `MessageFilter::in_timezone`).
+    pub fn in_timezone(&mut self, timezone: Tz) {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.in_timezone(timezone);
+                b.in_timezone(timezone);
+            }
+            Expr::Not(e) => e.in_timezone(timezone),
+            Expr::Pred(Predicate::Date {
+                start, end, day: Some(day), ..
+            }) => {
+                if let Some(next) = day.checked_add_days(Days::new(1)) {
+                    (*start, *end) = (start_of_day(*day, timezone), start_of_day(next, timezone));
+                }
+            }
+            Expr::Pred(_) => {}
+        }
+    }
+
+    pub fn matches(&self, msg: &Message) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(msg) && b.matches(msg),
+            Expr::Or(a, b) => a.matches(msg) || b.matches(msg),
+            Expr::Not(e) => !e.matches(msg),
+            Expr::Pred(p) => p.matches(msg),
+        }
+    }
+}
+
+impl Predicate {
+    fn matches(&self, msg: &Message) -> bool {
+        match self {
+            Predicate::From(user) => is_sender(msg, user),
+            Predicate::Chat(chat) => {
+                msg.chat.id.is_some_and(|id| id.to_string() == *chat)
+                    || msg.chat.name.as_deref() == Some(chat.as_str())
+            }
+            Predicate::ForwardedFrom(name) => msg.forwarded_from.as_deref() == Some(name.as_str()),
+            Predicate::Date { op, start, end, .. } => match msg.date {
+                Some(date) => match op {
+                    CmpOp::Eq => date >= *start && date < *end,
+                    CmpOp::Ne => date < *start || date >= *end,
+                    CmpOp::Lt => date < *start,
+                    CmpOp::Le => date < *end,
+                    CmpOp::Gt => date >= *end,
+                    CmpOp::Ge => date >= *start,
+                },
+                None => false,
+            },
+            Predicate::Id(op, id) => compare(*op, msg.id, *id),
+            Predicate::ReplyTo(id) => msg.reply_to_message_id == Some(*id),
+            Predicate::Type(kind) => msg.kind.as_str() == kind,
+            Predicate::Media(media) => msg.media_type.as_deref() == Some(media.as_str()),
+            Predicate::Has(HasKind::Link) => msg.has_link(),
+            Predicate::Has(HasKind::Media) => msg.has_media(),
+            Predicate::Has(HasKind::Photo) => msg.photo.is_some(),
+            Predicate::Has(HasKind::File) => msg.file.is_some(),
+            Predicate::Has(HasKind::Reply) | Predicate::Reply => msg.reply_to_message_id.is_some(),
+            Predicate::Has(HasKind::Text) => !msg.text.trim().is_empty(),
+            Predicate::TextContains(needle) => msg.text.to_lowercase().contains(needle.as_str()),
+            Predicate::TextRegex(re) => re.is_match(&msg.text),
+            Predicate::Forwarded => msg.is_forwarded(),
+            Predicate::Service => msg.is_service(),
+            Predicate::Bot => msg.is_bot(),
+            Predicate::ViaBot => msg.via_bot.is_some(),
+            Predicate::Edited => msg.edited.is_some(),
+        }
+    }
+}
+
+fn compare(op: CmpOp, left: i64, right: i64) -> bool {
+    match op {
+        CmpOp::Eq => left == right,
+        CmpOp::Ne => left != right,
+        CmpOp::Lt => left < right,
+        CmpOp::Le => left <= right,
+        CmpOp::Gt => left > right,
+        CmpOp::Ge => left >= right,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Lexer
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    /// A bare word without operator: a flag such as `forwarded`.
+    Flag(String),
+    /// `field op value`; `op` is `:`, `~` or a comparison.
+    Term {
+        field: String,
+        op: String,
+        value: String,
+        value_start: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, QueryError> {
+    let err = |message: String, start: usize, end: usize| QueryError {
+        message,
+        input: input.to_string(),
+        start,
+        end,
+    };
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == b'(' || c == b')' {
+            let kind = if c == b'(' { TokenKind::LParen } else { TokenKind::RParen };
+            tokens.push(Token { kind, start: i, end: i + 1 });
+            i += 1;
+            continue;
+        }
+
+        // Identifier: a field name, a flag or a keyword
+        let start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+            i += 1;
+        }
+        if i == start {
+            let end = input[start..].chars().next().map_or(start + 1, |ch| start + ch.len_utf8());
+            return Err(err(format!("Unexpected character “{}”", &input[start..end]), start, end));
+        }
+        let word = &input[start..i];
+
+        // Operator directly after the identifier makes it a term
+        let op = [">=", "<=", "!=", ":", "=", "<", ">", "~"]
+            .into_iter()
+            .find(|op| input[i..].starts_with(op));
+        let Some(op) = op else {
+            let kind = match word.to_ascii_uppercase().as_str() {
+                "AND" => TokenKind::And,
+                "OR" => TokenKind::Or,
+                "NOT" => TokenKind::Not,
+                _ => TokenKind::Flag(word.to_string()),
+            };
+            tokens.push(Token { kind, start, end: i });
+            continue;
+        };
+        i += op.len();
+
+        // Value: quoted string or bare run up to whitespace / parenthesis
+        let value_start = i;
+        let value = if bytes.get(i) == Some(&b'"') {
+            i += 1;
+            let mut value = String::new();
+            let mut closed = false;
+            let mut chars = input[i..].char_indices();
+            while let Some((offset, ch)) = chars.next() {
+                match ch {
+                    '"' => {
+                        i += offset + 1;
+                        closed = true;
+                        break;
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, escaped)) => value.push(escaped),
+                        None => break,
+                    },
+                    _ => value.push(ch),
+                }
+            }
+            if !closed {
+                return Err(err("Unterminated string".to_string(), value_start, input.len()));
+            }
+            value
+        } else {
+            let rest = &input[i..];
+            let len = rest
+                .find(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')')
+                .unwrap_or(rest.len());
+            i += len;
+            rest[..len].to_string()
+        };
+        if value.is_empty() && !input[value_start..i].starts_with('"') {
+            return Err(err(format!("Missing value after “{}{}”", word, op), start, i));
+        }
+
+        tokens.push(Token {
+            kind: TokenKind::Term {
+                field: word.to_string(),
+                op: op.to_string(),
+                value,
+                value_start,
+            },
+            start,
+            end: i,
+        });
+    }
+    Ok(tokens)
+}
+
+// ----------------------------------------------------------------------------
+// Parser
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn error(&self, message: impl Into<String>, start: usize, end: usize) -> QueryError {
+        QueryError {
+            message: message.into(),
+            input: self.input.to_string(),
+            start,
+            end,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_and()?;
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// `a AND b`, or just `a b`.
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_not()?;
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => self.pos += 1,
+                Some(TokenKind::Or) | Some(TokenKind::RParen) | None => break,
+                Some(_) => {}
+            }
+            let right = self.parse_not()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, QueryError> {
+        if self.peek().is_some_and(|t| t.kind == TokenKind::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        let Some(token) = self.peek().cloned() else {
+            let end = self.input.len();
+            return Err(self.error("Expression ends where a term was expected", end, end));
+        };
+        self.pos += 1;
+        match token.kind {
+            TokenKind::LParen => {
+                let inner = self.parse_or()?;
+                match self.peek() {
+                    Some(t) if t.kind == TokenKind::RParen => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(self.error("Unclosed parenthesis", token.start, token.end)),
+                }
+            }
+            TokenKind::RParen => Err(self.error("Unmatched closing parenthesis", token.start, token.end)),
+            TokenKind::And | TokenKind::Or | TokenKind::Not => Err(self.error(
+                "Expected a term before this operator",
+                token.start,
+                token.end,
+            )),
+            TokenKind::Flag(flag) => {
+                let predicate = match flag.to_ascii_lowercase().as_str() {
+                    "forwarded" => Predicate::Forwarded,
+                    "service" => Predicate::Service,
+                    "bot" => Predicate::Bot,
+                    "via_bot" => Predicate::ViaBot,
+                    "edited" => Predicate::Edited,
+                    "reply" => Predicate::Reply,
+                    _ => {
+                        return Err(self.error(
+                            format!(
+                                "Unknown flag “{}” (expected forwarded, service, bot, via_bot, edited or reply, \
+                                 or a field like from:…)",
+                                flag
+                            ),
+                            token.start,
+                            token.end,
+                        ))
+                    }
+                };
+                Ok(Expr::Pred(predicate))
+            }
+            TokenKind::Term {
+                field,
+                op,
+                value,
+                value_start,
+            } => self
+                .term(&field, &op, value, token.start, value_start, token.end)
+                .map(Expr::Pred),
+        }
+    }
+
+    fn term(
+        &self,
+        field: &str,
+        op: &str,
+        value: String,
+        start: usize,
+        value_start: usize,
+        end: usize,
+    ) -> Result<Predicate, QueryError> {
+        let bad_value = |message: String| self.error(message, value_start, end);
+        let field_lc = field.to_ascii_lowercase();
+        let op_end = value_start;
+        let op_start = op_end - op.len();
+
+        // Fields that only take `:` (or `=`)
+        let equality_only = |p: Predicate| -> Result<Predicate, QueryError> {
+            if op == ":" || op == "=" {
+                Ok(p)
+            } else {
+                Err(self.error(
+                    format!("Field “{}” only supports “:”", field),
+                    op_start,
+                    op_end,
+                ))
+            }
+        };
+
+        match field_lc.as_str() {
+            "from" => equality_only(Predicate::From(value)),
+            "chat" => equality_only(Predicate::Chat(value)),
+            "forwarded_from" => equality_only(Predicate::ForwardedFrom(value)),
+            "type" => equality_only(Predicate::Type(value.to_ascii_lowercase())),
+            "media" => equality_only(Predicate::Media(value)),
+            "reply" => {
+                let id = value
+                    .parse()
+                    .map_err(|_| bad_value(format!("Expected a message id, got “{}”", value)))?;
+                equality_only(Predicate::ReplyTo(id))
+            }
+            "has" => {
+                let kind = match value.to_ascii_lowercase().as_str() {
+                    "link" => HasKind::Link,
+                    "media" => HasKind::Media,
+                    "photo" => HasKind::Photo,
+                    "file" => HasKind::File,
+                    "reply" => HasKind::Reply,
+                    "text" => HasKind::Text,
+                    _ => {
+                        return Err(bad_value(format!(
+                            "Unknown has:{} (expected link, media, photo, file, reply or text)",
+                            value
+                        )))
+                    }
+                };
+                equality_only(Predicate::Has(kind))
+            }
+            "text" => match op {
+                ":" | "=" => Ok(Predicate::TextContains(value.to_lowercase())),
+                "~" => RegexBuilder::new(&value)
+                    .case_insensitive(true)
+                    .build()
+                    .map(Predicate::TextRegex)
+                    .map_err(|e| bad_value(format!("Invalid regex: {}", e))),
+                _ => Err(self.error("Field “text” supports “:” (contains) and “~” (regex)", op_start, op_end)),
+            },
+            "date" => {
+                let op = cmp_op(op).ok_or_else(|| {
+                    self.error("Field “date” supports :, =, !=, <, <=, >, >=", op_start, op_end)
+                })?;
+                let (start, end) = parse_period(&value).ok_or_else(|| {
+                    bad_value(format!("Invalid date “{}”: expected YYYY-MM-DD or RFC 3339", value))
+                })?;
+                let day = NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok();
+                Ok(Predicate::Date { op, start, end, day })
+            }
+            "id" => {
+                let op = cmp_op(op).ok_or_else(|| {
+                    self.error("Field “id” supports :, =, !=, <, <=, >, >=", op_start, op_end)
+                })?;
+                let id = value
+                    .parse()
+                    .map_err(|_| bad_value(format!("Expected a number, got “{}”", value)))?;
+                Ok(Predicate::Id(op, id))
+            }
+            _ => Err(self.error(
+                format!(
+                    "Unknown field “{}” (expected from, chat, date, id, reply, type, media, has, text or forwarded_from)",
+                    field
+                ),
+                start,
+                start + field.len(),
+            )),
+        }
+    }
+}
+
+fn cmp_op(op: &str) -> Option<CmpOp> {
+    match op {
+        ":" | "=" => Some(CmpOp::Eq),
+        "!=" => Some(CmpOp::Ne),
+        "<" => Some(CmpOp::Lt),
+        "<=" => Some(CmpOp::Le),
+        ">" => Some(CmpOp::Gt),
+        ">=" => Some(CmpOp::Ge),
+        _ => None,
+    }
+}
+
+/// A plain date covers that whole UTC day; a timestamp covers its second.
+fn parse_period(s: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    if let Ok(day) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let start = day.and_hms_opt(0, 0, 0)?.and_utc();
+        let end = day.checked_add_days(Days::new(1))?.and_hms_opt(0, 0, 0)?.and_utc();
+        return Some((start, end));
+    }
+    let start = DateTime::parse_from_rfc3339(s).ok()?.with_timezone(&Utc);
+    Some((start, start + TimeDelta::seconds(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Chat;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// The tree of `input`, fully parenthesized, with flags by name.
+    fn shape(input: &str) -> String {
+        fn show(expr: &Expr) -> String {
+            match expr {
+                Expr::And(a, b) => format!("({} & {})", show(a), show(b)),
+                Expr::Or(a, b) => format!("({} | {})", show(a), show(b)),
+                Expr::Not(e) => format!("!{}", show(e)),
+                Expr::Pred(p) => format!("{:?}", p).to_lowercase(),
+            }
+        }
+        show(&Expr::parse(input).unwrap())
+    }
+
+    fn message(date: Option<&str>) -> Message {
+        let chat = Arc::new(Chat {
+            id: Some(1),
+            name: Some("Chat".to_string()),
+            kind: Some("private_group".to_string()),
+        });
+        let value = json!({
+            "id": 7, "type": "message", "date": date, "from": "Ann", "from_id": "user1", "text": "hi",
+        });
+        Message::from_json(&value, chat).unwrap()
+    }
+
+    fn matches(input: &str, msg: &Message) -> bool {
+        Expr::parse(input).unwrap().matches(msg)
+    }
+
+    #[test]
+    fn not_binds_tightest_then_and_then_or() {
+        assert_eq!(shape("forwarded OR service AND bot"), "(forwarded | (service & bot))");
+        assert_eq!(shape("forwarded AND service OR bot"), "((forwarded & service) | bot)");
+        assert_eq!(shape("NOT forwarded AND service"), "(!forwarded & service)");
+        assert_eq!(shape("NOT (forwarded OR service)"), "!(forwarded | service)");
+        assert_eq!(shape("forwarded or not bot"), "(forwarded | !bot)");
+    }
+
+    #[test]
+    fn adjacent_terms_are_anded() {
+        assert_eq!(shape("forwarded service OR bot"), "((forwarded & service) | bot)");
+        assert_eq!(shape("bot OR forwarded NOT service"), "(bot | (forwarded & !service))");
+    }
+
+    #[test]
+    fn quoted_values_unescape() {
+        let Expr::Pred(Predicate::From(name)) = Expr::parse(r#"from:"Jane Doe""#).unwrap() else {
+            panic!("expected a from: term");
+        };
+        assert_eq!(name, "Jane Doe");
+        let Expr::Pred(Predicate::TextContains(text)) = Expr::parse(r#"text:"Say \"Hi\" \\ now""#).unwrap() else {
+            panic!("expected a text: term");
+        };
+        assert_eq!(text, r#"say "hi" \ now"#);
+        // A quoted value may hold what would end a bare one
+        assert_eq!(shape(r#"text:"a (b) OR c" bot"#), "(textcontains(\"a (b) or c\") & bot)");
+    }
+
+    #[test]
+    fn unterminated_string_points_at_its_start() {
+        let e = Expr::parse(r#"bot AND text:"open"#).unwrap_err();
+        assert_eq!(e.message, "Unterminated string");
+        assert_eq!((e.start, e.end), (13, 18));
+    }
+
+    #[test]
+    fn plain_dates_compare_as_whole_days() {
+        let msg = message(Some("2024-01-31T10:00:00"));
+        for (input, expected) in [
+            ("date:2024-01-31", true),
+            ("date=2024-01-31", true),
+            ("date!=2024-01-31", false),
+            ("date<2024-01-31", false),
+            ("date<=2024-01-31", true),
+            ("date>2024-01-31", false),
+            ("date>=2024-01-31", true),
+            ("date>2024-01-30", true),
+            ("date<2024-02-01", true),
+            ("date!=2024-02-01", true),
+        ] {
+            assert_eq!(matches(input, &msg), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn plain_dates_are_days_of_the_timezone() {
+        // 23:30 UTC on the 31st is already February 1st in Berlin
+        let msg = message(Some("2024-01-31T23:30:00"));
+        let mut expr = Expr::parse("date:2024-02-01").unwrap();
+        assert!(!expr.matches(&msg));
+        expr.in_timezone(chrono_tz::Europe::Berlin);
+        assert!(expr.matches(&msg));
+
+        let mut expr = Expr::parse("date>=2024-01-31T23:00:00Z").unwrap();
+        expr.in_timezone(chrono_tz::Europe::Berlin);
+        assert!(expr.matches(&msg));
+    }
+
+    #[test]
+    fn timestamps_compare_to_the_second() {
+        let msg = message(Some("2024-01-31T10:00:00"));
+        for (input, expected) in [
+            ("date:2024-01-31T10:00:00Z", true),
+            ("date>=2024-01-31T10:00:00Z", true),
+            ("date>2024-01-31T10:00:00Z", false),
+            ("date<2024-01-31T10:00:01Z", true),
+            ("date<=2024-01-31T09:59:59Z", false),
+            ("date>2024-01-31T11:00:00+02:00", true),
+        ] {
+            assert_eq!(matches(input, &msg), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn messages_without_a_date_match_no_date_term() {
+        let msg = message(None);
+        for op in [":", "!=", "<", "<=", ">", ">="] {
+            assert!(!matches(&format!("date{}2024-01-31", op), &msg), "{}", op);
+        }
+        assert!(matches("NOT date<2024-01-31", &msg));
+    }
+
+    #[test]
+    fn bot_is_the_sender_and_via_bot_the_inline_bot() {
+        let mut msg = message(Some("2024-01-31T10:00:00"));
+        msg.via_bot = Some("@gif".to_string());
+        assert!(matches("via_bot", &msg));
+        assert!(!matches("bot", &msg));
+
+        let chat = Arc::new(Chat {
+            id: Some(555),
+            name: Some("Helper".to_string()),
+            kind: Some("bot_chat".to_string()),
+        });
+        let value = json!({
+            "id": 8, "type": "message", "date": "2024-01-31T10:00:00", "from_id": "user555", "text": "hi",
+        });
+        let reply = Message::from_json(&value, chat).unwrap();
+        assert!(matches("bot AND NOT via_bot", &reply));
+    }
+
+    #[test]
+    fn caret_sits_under_the_bad_token() {
+        let e = Expr::parse("from:bob AND & bot").unwrap_err();
+        assert_eq!((e.start, e.end), (13, 14));
+        assert_eq!(e.to_string(), "Unexpected character “&”\n  from:bob AND & bot\n               ^");
+
+        let e = Expr::parse("bot AND fro:bob").unwrap_err();
+        assert_eq!((e.start, e.end), (8, 11));
+        assert!(e.to_string().ends_with("\n  bot AND fro:bob\n          ^^^"));
+
+        let e = Expr::parse("date>=2024-13-01").unwrap_err();
+        assert_eq!(&e.input[e.start..e.end], "2024-13-01");
+
+        let e = Expr::parse("bot OR").unwrap_err();
+        assert_eq!((e.start, e.end), (6, 6));
+        assert!(e.to_string().ends_with("\n  bot OR\n        ^"));
+    }
+}
@@ -0,0 +1,451 @@
+// src/report.rs
+
+use crate::common::OutputFormat;
+use crate::error::AnalyzerError;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+/// Type of a report column, kept alongside the rows so typed formats
+/// (JSON, and the sidecar schema of CSV/TSV) don't have to guess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// Free text (URLs, names, message text).
+    Text,
+    /// Text drawn from a small fixed set of values (`link`/`media`, metric names).
+    Category,
+    Integer,
+    Float,
+    Date,
+    Timestamp,
+}
+
+impl ColumnType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColumnType::Text => "text",
+            ColumnType::Category => "category",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::Timestamp => "timestamp",
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, ColumnType::Integer | ColumnType::Float)
+    }
+}
+
+/// A named, typed report column.
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: ColumnType,
+}
+
+impl Column {
+    pub const fn new(name: &'static str, kind: ColumnType) -> Self {
+        Column { name, kind }
+    }
+}
+
+/// One value of a report row. `Null` renders as an empty field (`null` in JSON).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Null,
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Date(NaiveDate),
+    Time(DateTime<Utc>),
+}
+
+impl Cell {
+    /// The cell as plain text, as it appears in text, CSV, TSV and Markdown output.
+    pub fn to_text(&self) -> String {
+        match self {
+            Cell::Null => String::new(),
+            Cell::Text(s) => s.clone(),
+            Cell::Int(n) => n.to_string(),
+            Cell::Float(x) => x.to_string(),
+            Cell::Date(d) => d.format("%Y-%m-%d").to_string(),
+            Cell::Time(t) => t.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Cell::Null => Value::Null,
+            Cell::Int(n) => json!(n),
+            Cell::Float(x) => json!(x),
+            other => Value::String(other.to_text()),
+        }
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(s: &str) -> Self {
+        Cell::Text(s.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(s: String) -> Self {
+        Cell::Text(s)
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(n: i64) -> Self {
+        Cell::Int(n)
+    }
+}
+
+impl From<usize> for Cell {
+    fn from(n: usize) -> Self {
+        Cell::Int(n as i64)
+    }
+}
+
+impl From<u64> for Cell {
+    fn from(n: u64) -> Self {
+        Cell::Int(n as i64)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(x: f64) -> Self {
+        Cell::Float(x)
+    }
+}
+
+impl From<NaiveDate> for Cell {
+    fn from(d: NaiveDate) -> Self {
+        Cell::Date(d)
+    }
+}
+
+impl From<DateTime<Utc>> for Cell {
+    fn from(t: DateTime<Utc>) -> Self {
+        Cell::Time(t)
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Cell::Null)
+    }
+}
+
+/// A behavior's results as a table: a column schema, rows of cells, and a
+/// summary block kept apart from the rows. Every `OutputFormat` renders from it:
+///
+///   - `Stdout` prints a header and tab-separated rows, then `summary_line`.
+///   - `Txt` writes the same to `<output_dir>/<file_stem>.txt`.
+///   - `Csv` writes RFC 4180 CSV (header, quoted only where needed, CRLF) to
+///     `<file_stem>.csv`, and the summary and column types to `<file_stem>.meta.json`.
+///   - `Tsv` writes tab-separated values to `<file_stem>.tsv` (tabs, newlines and
+///     backslashes in cells escaped as `\t`, `\n`, `\\`), plus the same `.meta.json`.
+///   - `Json` writes `{ "behavior", "schema_version", "summary", "columns", "rows" }`
+///     to `<file_stem>.json`.
+///   - `Jsonl` writes one row per line to `<file_stem>.jsonl`, each tagged
+///     with `behavior` and `schema_version`.
+///   - `Markdown` writes the summary as a list followed by a table to `<file_stem>.md`.
+///   - `Parquet` and `Arrow` write typed columnar files (`<file_stem>.parquet`,
+///     `<file_stem>.arrow`); see `columnar::encode`. They need the `arrow` feature.
+pub struct Report {
+    pub behavior: &'static str,
+    pub file_stem: &'static str,
+    /// Version of the columns and summary (see `BehaviorInfo::schema_version`).
+    pub schema_version: u32,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Cell>>,
+    pub summary: Vec<(&'static str, Cell)>,
+    pub summary_line: String,
+}
+
+impl Report {
+    /// Renders the report in `format`: to stdout for `Stdout`, otherwise into
+    /// `<output_dir>/<file_stem>.<ext>` (the current folder if `output_dir` is
+    /// `None`), whose path is returned.
+    pub fn write(&self, format: &OutputFormat, output_dir: Option<&str>) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        if *format == OutputFormat::Stdout {
+            let mut out = io::stdout().lock();
+            self.render(format, &mut out)?;
+            return Ok(None);
+        }
+
+        // Render first so a failure (e.g. columnar output without the feature) leaves no file behind
+        let mut body = Vec::new();
+        self.render(format, &mut body)?;
+        let (output_path, mut out) = create_output(output_dir, self.file_stem, extension(format))?;
+        out.write_all(&body).map_err(AnalyzerError::write(&output_path))?;
+        out.flush().map_err(AnalyzerError::write(&output_path))?;
+
+        // CSV and TSV have nowhere to put the summary, so it goes next to them
+        if matches!(format, OutputFormat::Csv | OutputFormat::Tsv) {
+            let stem = format!("{}.meta", self.file_stem);
+            let (meta_path, mut meta) = create_output(output_dir, &stem, "json")?;
+            serde_json::to_writer_pretty(&mut meta, &self.metadata())
+                .map_err(|e| AnalyzerError::write(&meta_path)(e.into()))?;
+            writeln!(meta).and_then(|_| meta.flush()).map_err(AnalyzerError::write(&meta_path))?;
+        }
+
+        Ok(Some(output_path))
+    }
+
+    /// Renders the report body in `format` into `out` (`Stdout` and `Txt` render the same).
+    pub fn render(&self, format: &OutputFormat, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        match format {
+            OutputFormat::Stdout | OutputFormat::Txt => {
+                self.write_delimited(out, "\t", "\n", |s| s.to_string())?;
+                writeln!(out, "{}", self.summary_line)?;
+            }
+            OutputFormat::Csv => self.write_delimited(out, ",", "\r\n", csv_field)?,
+            OutputFormat::Tsv => self.write_delimited(out, "\t", "\n", tsv_field)?,
+            OutputFormat::Json => {
+                let mut document = self.metadata();
+                document.insert(
+                    "rows".to_string(),
+                    Value::Array(self.rows.iter().map(|r| Value::Object(self.row_json(r))).collect()),
+                );
+                serde_json::to_writer_pretty(&mut *out, &document)?;
+                writeln!(out)?;
+            }
+            OutputFormat::Jsonl => {
+                for row in &self.rows {
+                    let mut line = Map::new();
+                    line.insert("behavior".to_string(), Value::from(self.behavior));
+                    line.insert("schema_version".to_string(), Value::from(self.schema_version));
+                    line.extend(self.row_json(row));
+                    serde_json::to_writer(&mut *out, &line)?;
+                    writeln!(out)?;
+                }
+            }
+            OutputFormat::Markdown => self.write_markdown(out, usize::MAX)?,
+            OutputFormat::Parquet | OutputFormat::Arrow => out.write_all(&self.encode_columnar(format)?)?,
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "arrow")]
+    fn encode_columnar(&self, format: &OutputFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+        crate::columnar::encode(self, format)
+    }
+
+    #[cfg(not(feature = "arrow"))]
+    fn encode_columnar(&self, format: &OutputFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+        Err(format!(
+            "{} output needs a build with the `arrow` feature (cargo build --features arrow)",
+            extension(format)
+        )
+        .into())
+    }
+
+    /// The summary entry called `key`.
+    pub fn summary_value(&self, key: &str) -> Option<&Cell> {
+        self.summary.iter().find(|(k, _)| *k == key).map(|(_, cell)| cell)
+    }
+
+    /// The summary block as a JSON object.
+    pub fn summary_json(&self) -> Value {
+        Value::Object(
+            self.summary
+                .iter()
+                .map(|(key, cell)| (key.to_string(), cell.to_json()))
+                .collect(),
+        )
+    }
+
+    /// `behavior`, `schema_version`, `summary` and the column schema.
+    fn metadata(&self) -> Map<String, Value> {
+        let columns: Vec<Value> = self
+            .columns
+            .iter()
+            .map(|c| json!({ "name": c.name, "type": c.kind.as_str() }))
+            .collect();
+        let mut meta = Map::new();
+        meta.insert("behavior".to_string(), json!(self.behavior));
+        meta.insert("schema_version".to_string(), json!(self.schema_version));
+        meta.insert("summary".to_string(), self.summary_json());
+        meta.insert("columns".to_string(), Value::Array(columns));
+        meta
+    }
+
+    fn row_json(&self, row: &[Cell]) -> Map<String, Value> {
+        self.columns
+            .iter()
+            .zip(row)
+            .map(|(column, cell)| (column.name.to_string(), cell.to_json()))
+            .collect()
+    }
+
+    fn write_delimited(
+        &self,
+        out: &mut dyn Write,
+        separator: &str,
+        terminator: &str,
+        field: fn(&str) -> String,
+    ) -> io::Result<()> {
+        let header: Vec<String> = self.columns.iter().map(|c| field(c.name)).collect();
+        write!(out, "{}{}", header.join(separator), terminator)?;
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(|cell| field(&cell.to_text())).collect();
+            write!(out, "{}{}", fields.join(separator), terminator)?;
+        }
+        Ok(())
+    }
+
+    /// A `## behavior` heading, the summary as a list, then the first `limit` rows as a GFM table.
+    pub fn write_markdown(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        writeln!(out, "## {}", self.behavior)?;
+        writeln!(out)?;
+        for (key, cell) in &self.summary {
+            writeln!(out, "- **{}**: {}", key, markdown_field(&cell.to_text()))?;
+        }
+        if !self.summary.is_empty() {
+            writeln!(out)?;
+        }
+        self.write_markdown_table(out, limit)
+    }
+
+    /// The first `limit` rows as a GFM table; URLs become links.
+    pub fn write_markdown_table(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let header: Vec<&str> = self.columns.iter().map(|c| c.name).collect();
+        let align: Vec<&str> = self
+            .columns
+            .iter()
+            .map(|c| if c.kind.is_numeric() { "---:" } else { "---" })
+            .collect();
+        writeln!(out, "| {} |", header.join(" | "))?;
+        writeln!(out, "| {} |", align.join(" | "))?;
+        for row in self.rows.iter().take(limit) {
+            let fields: Vec<String> = row.iter().map(|cell| markdown_cell(&cell.to_text())).collect();
+            writeln!(out, "| {} |", fields.join(" | "))?;
+        }
+        if self.rows.len() > limit {
+            writeln!(out)?;
+            writeln!(out, "_{} more rows not shown._", self.rows.len() - limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// File extension used for `format`.
+pub fn extension(format: &OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Stdout | OutputFormat::Txt => "txt",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Tsv => "tsv",
+        OutputFormat::Json => "json",
+        OutputFormat::Jsonl => "jsonl",
+        OutputFormat::Markdown => "md",
+        OutputFormat::Parquet => "parquet",
+        OutputFormat::Arrow => "arrow",
+    }
+}
+
+/// Creates `<output_dir>/<file_stem>.<extension>` (and the folder), defaulting to `.`.
+/// Failures are `AnalyzerError::Write`.
+pub fn create_output(
+    output_dir: Option<&str>,
+    file_stem: &str,
+    extension: &str,
+) -> Result<(PathBuf, BufWriter<File>), Box<dyn Error>> {
+    let dir = output_dir.unwrap_or(".");
+    fs::create_dir_all(dir).map_err(AnalyzerError::write(dir))?;
+    let mut output_path = PathBuf::from(dir);
+    output_path.push(format!("{}.{}", file_stem, extension));
+    let file = File::create(&output_path).map_err(AnalyzerError::write(&output_path))?;
+    Ok((output_path, BufWriter::new(file)))
+}
+
+/// RFC 4180 field: quoted (with `"` doubled) only if it contains a comma, quote or line break.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn tsv_field(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Like `markdown_field`, but a URL becomes a `[url](url)` link.
+fn markdown_cell(s: &str) -> String {
+    if (s.starts_with("http://") || s.starts_with("https://")) && !s.contains(char::is_whitespace) {
+        let target = s.replace('(', "%28").replace(')', "%29").replace('|', "%7C");
+        format!("[{}]({})", markdown_field(s).replace('[', "\\[").replace(']', "\\]"), target)
+    } else {
+        markdown_field(s)
+    }
+}
+
+fn markdown_field(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_only_fields_that_need_it() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\ronly"), "\"cr\ronly\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_line_breaks_and_backslashes() {
+        assert_eq!(tsv_field("a\tb"), "a\\tb");
+        assert_eq!(tsv_field("one\r\ntwo"), "one\\r\\ntwo");
+        assert_eq!(tsv_field("C:\\temp\\new"), "C:\\\\temp\\\\new");
+        assert_eq!(tsv_field("a,\"b\""), "a,\"b\"");
+    }
+
+    #[test]
+    fn markdown_cells_keep_the_table_intact() {
+        assert_eq!(markdown_cell("a | b"), "a \\| b");
+        assert_eq!(markdown_cell("one\r\ntwo\nthree"), "one<br>two<br>three");
+        assert_eq!(markdown_cell("back\\slash"), "back\\\\slash");
+        assert_eq!(
+            markdown_cell("https://example.com/a_(b)?x=1|2"),
+            "[https://example.com/a_(b)?x=1\\|2](https://example.com/a_%28b%29?x=1%7C2)"
+        );
+        assert_eq!(markdown_cell("https://example.com/[x]"), "[https://example.com/\\[x\\]](https://example.com/[x])");
+        // Text that merely starts with a URL stays text
+        assert_eq!(markdown_cell("https://example.com and more"), "https://example.com and more");
+    }
+
+    #[test]
+    fn csv_rows_end_in_crlf_and_survive_awkward_text() {
+        let report = Report {
+            behavior: "test",
+            file_stem: "test",
+            schema_version: 1,
+            columns: vec![Column::new("id", ColumnType::Integer), Column::new("text", ColumnType::Text)],
+            rows: vec![vec![1usize.into(), "hi, \"you\"\nthere".into()], vec![2usize.into(), Cell::Null]],
+            summary: Vec::new(),
+            summary_line: String::new(),
+        };
+        let mut out = Vec::new();
+        report.render(&OutputFormat::Csv, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "id,text\r\n1,\"hi, \"\"you\"\"\nthere\"\r\n2,\r\n");
+    }
+}
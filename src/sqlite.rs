@@ -0,0 +1,269 @@
+// src/sqlite.rs
+
+use crate::behaviors::shares::media_name;
+use crate::error::AnalyzerError;
+use crate::model::{Chat, Message};
+use crate::report::{Cell, ColumnType, Report};
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Schema of the normalized dataset. Messages are keyed by `(chat_id, id)`;
+/// every per-message table references them. `reply_to_message_id` has no
+/// foreign key because replies often point at messages outside the export.
+const SCHEMA: &str = "
+CREATE TABLE chats (
+    id          INTEGER PRIMARY KEY,
+    telegram_id INTEGER,
+    name        TEXT,
+    type        TEXT
+);
+CREATE TABLE users (
+    id   TEXT PRIMARY KEY,
+    name TEXT
+);
+CREATE TABLE messages (
+    chat_id        INTEGER NOT NULL REFERENCES chats(id),
+    id             INTEGER NOT NULL,
+    type           TEXT NOT NULL,
+    date           TEXT,
+    date_unixtime  INTEGER,
+    edited         TEXT,
+    user_id        TEXT REFERENCES users(id),
+    from_name      TEXT,
+    text           TEXT NOT NULL,
+    forwarded_from TEXT,
+    via_bot        TEXT,
+    source         TEXT,
+    PRIMARY KEY (chat_id, id)
+);
+CREATE TABLE entities (
+    chat_id    INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    position   INTEGER NOT NULL,
+    type       TEXT NOT NULL,
+    text       TEXT NOT NULL,
+    href       TEXT,
+    PRIMARY KEY (chat_id, message_id, position),
+    FOREIGN KEY (chat_id, message_id) REFERENCES messages(chat_id, id)
+);
+CREATE TABLE links (
+    chat_id    INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    url        TEXT NOT NULL,
+    FOREIGN KEY (chat_id, message_id) REFERENCES messages(chat_id, id)
+);
+CREATE TABLE media (
+    chat_id    INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    name       TEXT NOT NULL,
+    media_type TEXT,
+    file       TEXT,
+    photo      TEXT,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id, message_id) REFERENCES messages(chat_id, id)
+);
+CREATE TABLE reactions (
+    chat_id    INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    type       TEXT NOT NULL,
+    emoji      TEXT,
+    count      INTEGER NOT NULL,
+    FOREIGN KEY (chat_id, message_id) REFERENCES messages(chat_id, id)
+);
+CREATE TABLE replies (
+    chat_id             INTEGER NOT NULL,
+    message_id          INTEGER NOT NULL,
+    reply_to_message_id INTEGER NOT NULL,
+    PRIMARY KEY (chat_id, message_id),
+    FOREIGN KEY (chat_id, message_id) REFERENCES messages(chat_id, id)
+);
+CREATE TABLE reports (
+    behavior       TEXT PRIMARY KEY,
+    table_name     TEXT NOT NULL,
+    schema_version INTEGER NOT NULL,
+    summary        TEXT NOT NULL
+);
+CREATE INDEX messages_user ON messages(user_id);
+CREATE INDEX messages_date ON messages(date);
+CREATE INDEX links_url ON links(url);
+CREATE INDEX replies_target ON replies(chat_id, reply_to_message_id);
+";
+
+/// Row counts reported by [`export_sqlite`].
+#[derive(Debug, Default)]
+pub struct SqliteStats {
+    pub chats: usize,
+    pub users: usize,
+    pub messages: usize,
+    pub reports: usize,
+}
+
+/// Writes the messages and the given behavior reports into a new SQLite
+/// database at `path` (replacing any existing file).
+///
+///   - The dataset goes into `chats`, `users`, `messages`, `entities`, `links`,
+///     `media`, `reactions` and `replies`.
+///   - Each report becomes a table named after its behavior (`count-urls` →
+///     `report_count_urls`), with its summary in `reports`.
+///
+/// Timestamps are stored as RFC 3339 text in UTC, which SQLite's date functions understand.
+pub fn export_sqlite(messages: &[Message], reports: &[Report], path: &Path) -> Result<SqliteStats, Box<dyn Error>> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(AnalyzerError::write(dir))?;
+    }
+    if path.exists() {
+        fs::remove_file(path).map_err(AnalyzerError::write(path))?;
+    }
+    let mut conn = Connection::open(path).map_err(|e| AnalyzerError::write(path)(io::Error::other(e)))?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut stats = SqliteStats::default();
+
+    // 1) Chats (matched like `merge` does: by id, by name for chats without one)
+    let mut chat_keys: HashMap<(Option<i64>, Option<&str>), i64> = HashMap::new();
+    for msg in messages {
+        let key = chat_key(&msg.chat);
+        if !chat_keys.contains_key(&key) {
+            let rowid = chat_keys.len() as i64 + 1;
+            tx.execute(
+                "INSERT INTO chats (id, telegram_id, name, type) VALUES (?1, ?2, ?3, ?4)",
+                params![rowid, msg.chat.id, msg.chat.name, msg.chat.kind],
+            )?;
+            chat_keys.insert(key, rowid);
+        }
+    }
+    stats.chats = chat_keys.len();
+
+    // 2) Users, under the name of their latest message
+    let mut users: HashMap<&str, (Option<DateTime<Utc>>, Option<&str>)> = HashMap::new();
+    for msg in messages {
+        let Some(id) = msg.from_id.as_deref() else { continue };
+        let user = users.entry(id).or_insert((None, None));
+        if msg.from.is_some() && msg.date >= user.0 {
+            *user = (msg.date, msg.from.as_deref());
+        }
+    }
+    for (id, (_, name)) in &users {
+        tx.execute("INSERT INTO users (id, name) VALUES (?1, ?2)", params![id, name])?;
+    }
+    stats.users = users.len();
+
+    // 3) Messages and everything hanging off them
+    {
+        let mut insert_message = tx.prepare(
+            "INSERT OR IGNORE INTO messages (chat_id, id, type, date, date_unixtime, edited, user_id,
+                                             from_name, text, forwarded_from, via_bot, source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )?;
+        let mut insert_entity = tx.prepare(
+            "INSERT INTO entities (chat_id, message_id, position, type, text, href) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let mut insert_link = tx.prepare("INSERT INTO links (chat_id, message_id, url) VALUES (?1, ?2, ?3)")?;
+        let mut insert_media = tx.prepare(
+            "INSERT INTO media (chat_id, message_id, name, media_type, file, photo) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let mut insert_reaction = tx.prepare(
+            "INSERT INTO reactions (chat_id, message_id, type, emoji, count) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut insert_reply =
+            tx.prepare("INSERT INTO replies (chat_id, message_id, reply_to_message_id) VALUES (?1, ?2, ?3)")?;
+
+        for msg in messages {
+            let chat = chat_keys[&chat_key(&msg.chat)];
+            let inserted = insert_message.execute(params![
+                chat,
+                msg.id,
+                msg.kind.as_str(),
+                msg.date.map(|d| d.to_rfc3339()),
+                msg.date.map(|d| d.timestamp()),
+                msg.edited.map(|d| d.to_rfc3339()),
+                msg.from_id,
+                msg.from,
+                msg.text,
+                msg.forwarded_from,
+                msg.via_bot,
+                msg.source.as_deref(),
+            ])?;
+            if inserted == 0 {
+                // A repeated (chat, id) in the input; keep the first copy
+                continue;
+            }
+            stats.messages += 1;
+            for (position, entity) in msg.entities.iter().enumerate() {
+                insert_entity.execute(params![chat, msg.id, position as i64, entity.kind, entity.text, entity.href])?;
+            }
+            for url in msg.links() {
+                insert_link.execute(params![chat, msg.id, url])?;
+            }
+            if let Some(name) = media_name(msg) {
+                insert_media.execute(params![chat, msg.id, name, msg.media_type, msg.file, msg.photo])?;
+            }
+            for reaction in &msg.reactions {
+                insert_reaction.execute(params![chat, msg.id, reaction.kind, reaction.emoji, reaction.count])?;
+            }
+            if let Some(target) = msg.reply_to_message_id {
+                insert_reply.execute(params![chat, msg.id, target])?;
+            }
+        }
+    }
+
+    // 4) One table per report
+    for report in reports {
+        let table = format!("report_{}", report.behavior.replace('-', "_"));
+        let columns: Vec<String> = report
+            .columns
+            .iter()
+            .map(|c| format!("{} {}", quote_identifier(c.name), sql_type(c.kind)))
+            .collect();
+        tx.execute_batch(&format!("CREATE TABLE {} ({});", quote_identifier(&table), columns.join(", ")))?;
+
+        let placeholders: Vec<String> = (1..=report.columns.len()).map(|n| format!("?{}", n)).collect();
+        let insert_sql = format!("INSERT INTO {} VALUES ({})", quote_identifier(&table), placeholders.join(", "));
+        let mut insert = tx.prepare(&insert_sql)?;
+        for row in &report.rows {
+            insert.execute(params_from_iter(row.iter().map(|cell| match cell {
+                Cell::Null => SqlValue::Null,
+                Cell::Int(n) => SqlValue::Integer(*n),
+                Cell::Float(x) => SqlValue::Real(*x),
+                other => SqlValue::Text(other.to_text()),
+            })))?;
+        }
+        tx.execute(
+            "INSERT INTO reports (behavior, table_name, schema_version, summary) VALUES (?1, ?2, ?3, ?4)",
+            params![report.behavior, table, report.schema_version, report.summary_json().to_string()],
+        )?;
+    }
+    stats.reports = reports.len();
+
+    tx.commit()?;
+    Ok(stats)
+}
+
+fn chat_key(chat: &Chat) -> (Option<i64>, Option<&str>) {
+    match chat.id {
+        Some(id) => (Some(id), None),
+        None => (None, chat.name.as_deref()),
+    }
+}
+
+/// `name` as a quoted SQL identifier: report and column names can come from
+/// plugins, so they are never spliced into SQL as they are.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn sql_type(kind: ColumnType) -> &'static str {
+    match kind {
+        ColumnType::Integer => "INTEGER",
+        ColumnType::Float => "REAL",
+        _ => "TEXT",
+    }
+}